@@ -2,25 +2,21 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
+    // Nothing to link when building without the SDL frontend
+    if env::var("CARGO_FEATURE_GUI").is_err() {
+        return;
+    }
+
     let target = env::var("TARGET").unwrap();
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Failed to find target dir"));
 
-    // Horrible brittle hack to allow me to send the game to friends
+    // The SDL DLLs have to sit next to the executable on Windows
     let mut build_output_dir = out_dir;
     build_output_dir.pop();
     build_output_dir.pop();
     build_output_dir.pop();
 
-    let mut src_resource_dir = manifest_dir.clone();
-    src_resource_dir.push("resources");
-    let mut dst_target_resource_dir = build_output_dir.clone();
-    dst_target_resource_dir.push("resources");
-    if !dst_target_resource_dir.exists() {
-        std::fs::create_dir(dst_target_resource_dir.clone()).unwrap();
-    }
-    copy(&src_resource_dir, &dst_target_resource_dir, ".png");
-
     if target.contains("pc-windows") {
         let mut lib_dir = manifest_dir.clone();
         let mut dll_dir = manifest_dir.clone();
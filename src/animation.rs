@@ -0,0 +1,217 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use threes::MoveResult;
+
+/// The share of an animation spent sliding tiles. The rest is spent popping
+/// merged tiles and bringing in the new card.
+const SLIDE_PORTION: f32 = 0.6;
+/// How much bigger a merged tile grows at the height of its pop
+const POP_GROWTH: f32 = 0.2;
+
+/// A tile to draw part way through an animation, at a fractional board cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub value: u32,
+    pub row: f32,
+    pub col: f32,
+    pub scale: f32,
+}
+
+/// Tweens the board from how it looked before a move to how it looks after,
+/// using the move's movements, merges and spawn.
+#[derive(Debug)]
+pub struct Animation {
+    before: [[u32; 4]; 4],
+    result: MoveResult,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    pub fn new(before: [[u32; 4]; 4], result: MoveResult, duration: Duration) -> Animation {
+        Animation {
+            before,
+            result,
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// How far through the animation we are, from 0 to 1.
+    fn progress(&self) -> f32 {
+        let duration = duration_secs(self.duration);
+        if duration <= 0.0 {
+            return 1.0;
+        }
+        (duration_secs(self.started.elapsed()) / duration).min(1.0)
+    }
+
+    /// The tiles to draw for the current frame, bottom first.
+    pub fn sprites(&self) -> Vec<Sprite> {
+        self.sprites_at(self.progress())
+    }
+
+    /// The tiles to draw `t` of the way through the animation.
+    fn sprites_at(&self, t: f32) -> Vec<Sprite> {
+        let slide = (t / SLIDE_PORTION).min(1.0);
+        let settle = ((t - SLIDE_PORTION) / (1.0 - SLIDE_PORTION)).max(0.0);
+        let result = &self.result;
+        let mut sprites = vec![];
+
+        // Tiles that stay put, including those being merged into
+        for (row, tiles) in self.before.iter().enumerate() {
+            for (col, &value) in tiles.iter().enumerate() {
+                if value == 0 || result.movements.iter().any(|m| m.from == (row, col)) {
+                    continue;
+                }
+                let merge = result.merges.iter().find(|m| m.into == (row, col));
+                sprites.push(match merge {
+                    Some(merge) if slide >= 1.0 => Sprite {
+                        value: merge.value,
+                        row: row as f32,
+                        col: col as f32,
+                        scale: 1.0 + POP_GROWTH * (PI * settle).sin(),
+                    },
+                    _ => Sprite {
+                        value,
+                        row: row as f32,
+                        col: col as f32,
+                        scale: 1.0,
+                    },
+                });
+            }
+        }
+
+        // Tiles sliding over, which vanish into the tile they merge with
+        for movement in result.movements.iter() {
+            let merging = result
+                .merges
+                .iter()
+                .any(|m| m.from == movement.from && m.into == movement.to);
+            if merging && slide >= 1.0 {
+                continue;
+            }
+            sprites.push(Sprite {
+                value: movement.value,
+                row: lerp(movement.from.0, movement.to.0, slide),
+                col: lerp(movement.from.1, movement.to.1, slide),
+                scale: 1.0,
+            });
+        }
+
+        // The new card slides in from just beyond the edge it spawns on
+        if let Some(spawn) = result.spawn {
+            if settle > 0.0 {
+                let (row_offset, col_offset) = result.direction.offset();
+                let (row, col) = (spawn.position.0 as f32, spawn.position.1 as f32);
+                sprites.push(Sprite {
+                    value: spawn.value,
+                    row: row - row_offset as f32 * (1.0 - settle),
+                    col: col - col_offset as f32 * (1.0 - settle),
+                    scale: 1.0,
+                });
+            }
+        }
+        sprites
+    }
+}
+
+fn lerp(from: usize, to: usize, amount: f32) -> f32 {
+    from as f32 + (to as f32 - from as f32) * amount
+}
+
+fn duration_secs(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+fn merge_left() -> Animation {
+    use threes::{Direction, Merge, Spawn, TileMove};
+
+    // A 2 slides left into a 1 to make a 3, then a 2 comes in on the right
+    let mut before = [[0; 4]; 4];
+    before[0] = [1, 2, 0, 0];
+    let result = MoveResult {
+        direction: Direction::Left,
+        moved: true,
+        movements: vec![TileMove {
+            from: (0, 1),
+            to: (0, 0),
+            value: 2,
+        }],
+        merges: vec![Merge {
+            from: (0, 1),
+            into: (0, 0),
+            value: 3,
+        }],
+        spawn: Some(Spawn {
+            position: (0, 3),
+            value: 2,
+        }),
+        next_card: 1,
+        score_delta: 3,
+    };
+    Animation::new(before, result, Duration::from_millis(150))
+}
+
+#[test]
+fn test_merge_slides_then_pops() {
+    let animation = merge_left();
+
+    let sliding = animation.sprites_at(0.3);
+    assert_eq!(sliding.len(), 2);
+    assert_eq!(
+        sliding[0],
+        Sprite {
+            value: 1,
+            row: 0.0,
+            col: 0.0,
+            scale: 1.0
+        }
+    );
+    assert_eq!(sliding[1].value, 2);
+    assert!((sliding[1].col - 0.5).abs() < 1e-4);
+
+    // Once the slide is done only the merged tile is left, popping
+    let popping: Vec<Sprite> = animation
+        .sprites_at(0.8)
+        .into_iter()
+        .filter(|sprite| sprite.col < 3.0)
+        .collect();
+    assert_eq!(popping.len(), 1);
+    assert_eq!(
+        (popping[0].value, popping[0].row, popping[0].col),
+        (3, 0.0, 0.0)
+    );
+    assert!((popping[0].scale - (1.0 + POP_GROWTH)).abs() < 1e-3);
+    assert_eq!(animation.sprites_at(1.0)[0].scale, 1.0);
+}
+
+#[test]
+fn test_spawn_only_appears_after_the_slide() {
+    let animation = merge_left();
+    let is_spawn = |sprite: &Sprite| sprite.value == 2 && sprite.col > 2.0;
+
+    for &t in &[0.0, 0.3, SLIDE_PORTION] {
+        assert!(!animation.sprites_at(t).iter().any(is_spawn), "at {}", t);
+    }
+    let coming_in = animation.sprites_at(0.8);
+    assert!(coming_in
+        .iter()
+        .any(|sprite| is_spawn(sprite) && sprite.col > 3.0));
+    let landed = animation.sprites_at(1.0);
+    assert_eq!(
+        landed.last(),
+        Some(&Sprite {
+            value: 2,
+            row: 0.0,
+            col: 3.0,
+            scale: 1.0
+        })
+    );
+}
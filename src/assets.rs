@@ -0,0 +1,180 @@
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the font is kept, relative to the resources folder
+const FONT_FILE: &str = "font/Raleway-Black.ttf";
+
+/// Artwork for the tiles that have it, used with the PNG skin: each value,
+/// the file it's kept in and the copy built into the binary. Every other
+/// value the engine can produce is drawn instead.
+const TILE_IMAGES: &[(u32, &str, &[u8])] = &[
+    (0, "zero.png", include_bytes!("../resources/zero.png")),
+    (1, "one.png", include_bytes!("../resources/one.png")),
+    (2, "two.png", include_bytes!("../resources/two.png")),
+    (3, "three.png", include_bytes!("../resources/three.png")),
+    (6, "six.png", include_bytes!("../resources/six.png")),
+    (12, "twelve.png", include_bytes!("../resources/twelve.png")),
+    (
+        24,
+        "twenty_four.png",
+        include_bytes!("../resources/twenty_four.png"),
+    ),
+    (
+        48,
+        "forty_eight.png",
+        include_bytes!("../resources/forty_eight.png"),
+    ),
+    (
+        96,
+        "ninety_six.png",
+        include_bytes!("../resources/ninety_six.png"),
+    ),
+    (
+        192,
+        "one_nine_two.png",
+        include_bytes!("../resources/one_nine_two.png"),
+    ),
+    (
+        384,
+        "three_eight_four.png",
+        include_bytes!("../resources/three_eight_four.png"),
+    ),
+];
+
+const EMBEDDED_FONT: &[u8] = include_bytes!("../resources/font/Raleway-Black.ttf");
+
+/// The font and tile artwork the window draws with. These are built into the
+/// binary, so the game runs from anywhere, but a skin can replace them all.
+#[derive(Clone)]
+pub struct Assets {
+    /// The folder the assets were read from, or `None` for the built in ones
+    dir: Option<PathBuf>,
+    font: Cow<'static, [u8]>,
+    tiles: Vec<(u32, Cow<'static, [u8]>)>,
+}
+
+/// Why a skin couldn't be loaded.
+#[derive(Debug)]
+pub enum AssetError {
+    /// The skin doesn't have these files, given relative to its folder
+    Missing(PathBuf, Vec<&'static str>),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssetError::Missing(ref dir, ref files) => {
+                write!(f, "{} is missing {}", dir.display(), files.join(", "))
+            }
+            AssetError::Io(ref path, ref e) => write!(f, "couldn't read {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for AssetError {}
+
+impl Assets {
+    /// The assets built into the binary.
+    pub fn embedded() -> Assets {
+        Assets {
+            dir: None,
+            font: Cow::Borrowed(EMBEDDED_FONT),
+            tiles: TILE_IMAGES
+                .iter()
+                .map(|&(value, _, bytes)| (value, Cow::Borrowed(bytes)))
+                .collect(),
+        }
+    }
+
+    /// Reads a skin laid out like `resources/`: the font in
+    /// `font/Raleway-Black.ttf` and, if `png_tiles`, a PNG for each tile.
+    /// Every file that's needed has to be there, so a half finished skin is
+    /// caught before the window opens.
+    pub fn from_dir(dir: &Path, png_tiles: bool) -> Result<Assets, AssetError> {
+        let tile_files = if png_tiles { TILE_IMAGES } else { &[] };
+        let missing: Vec<&'static str> = Some(FONT_FILE)
+            .into_iter()
+            .chain(tile_files.iter().map(|&(_, name, _)| name))
+            .filter(|name| !dir.join(name).is_file())
+            .collect();
+        if !missing.is_empty() {
+            return Err(AssetError::Missing(dir.to_path_buf(), missing));
+        }
+
+        let read = |name: &str| {
+            let path = dir.join(name);
+            fs::read(&path)
+                .map(Cow::Owned)
+                .map_err(|e| AssetError::Io(path, e))
+        };
+        let mut tiles = Vec::with_capacity(tile_files.len());
+        for &(value, name, _) in tile_files {
+            tiles.push((value, read(name)?));
+        }
+        Ok(Assets {
+            dir: Some(dir.to_path_buf()),
+            font: read(FONT_FILE)?,
+            tiles,
+        })
+    }
+
+    pub fn load_font<'ttf>(
+        &self,
+        ttf_context: &'ttf Sdl2TtfContext,
+        point_size: u16,
+    ) -> Result<Font<'ttf, '_>, String> {
+        ttf_context.load_font_from_rwops(RWops::from_bytes(&self.font)?, point_size)
+    }
+
+    /// The PNG artwork for `value`, if there is any.
+    pub fn tile_image(&self, value: u32) -> Option<&[u8]> {
+        self.tiles
+            .iter()
+            .find(|&&(tile, _)| tile == value)
+            .map(|(_, bytes)| &bytes[..])
+    }
+}
+
+impl fmt::Debug for Assets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Assets").field("dir", &self.dir).finish()
+    }
+}
+
+#[test]
+fn test_missing_files_are_all_listed() {
+    let dir = std::env::temp_dir().join(format!("threes-empty-skin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    match Assets::from_dir(&dir, true) {
+        Err(AssetError::Missing(path, files)) => {
+            assert_eq!(path, dir);
+            assert_eq!(files.len(), TILE_IMAGES.len() + 1);
+            assert_eq!(files[0], FONT_FILE);
+            for &(_, name, _) in TILE_IMAGES {
+                assert!(files.contains(&name), "{} not listed", name);
+            }
+            let message = AssetError::Missing(path, files).to_string();
+            assert!(message.contains("three_eight_four.png"), "{}", message);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    // Tiles drawn by the game don't need the artwork
+    match Assets::from_dir(&dir, false) {
+        Err(AssetError::Missing(_, files)) => assert_eq!(files, vec![FONT_FILE]),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    fs::create_dir_all(dir.join("font")).unwrap();
+    fs::write(dir.join(FONT_FILE), EMBEDDED_FONT).unwrap();
+    let assets = Assets::from_dir(&dir, false).unwrap();
+    assert_eq!(assets.tile_image(3), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
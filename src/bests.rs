@@ -0,0 +1,154 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use board::Board;
+use save::{data_dir, SaveError};
+
+const BESTS_HEADER: &str = "threes-bests 1";
+
+/// The best results across every game finished on this machine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PersonalBests {
+    pub score: u64,
+    pub high_card: u32,
+    /// The most moves played in a single game
+    pub moves: usize,
+    /// How many games have been finished
+    pub games: u32,
+}
+
+/// Which personal bests a finished game beat.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NewBests {
+    pub score: bool,
+    pub high_card: bool,
+    pub moves: bool,
+}
+
+impl PersonalBests {
+    /// Counts a finished game, keeping anything it did better than before.
+    pub fn record(&mut self, board: &Board) -> NewBests {
+        let score = board.calculate_score();
+        let moves = board.get_moves().len();
+        let new = NewBests {
+            score: score > self.score,
+            high_card: board.get_high_card() > self.high_card,
+            moves: moves > self.moves,
+        };
+        self.score = self.score.max(score);
+        self.high_card = self.high_card.max(board.get_high_card());
+        self.moves = self.moves.max(moves);
+        self.games += 1;
+        new
+    }
+
+    pub fn to_bests_string(&self) -> String {
+        format!(
+            "{}\nscore {}\nhigh_card {}\nmoves {}\ngames {}\n",
+            BESTS_HEADER, self.score, self.high_card, self.moves, self.games
+        )
+    }
+
+    pub fn from_bests_str(contents: &str) -> Result<PersonalBests, SaveError> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(BESTS_HEADER) {
+            return Err(malformed("not a threes personal bests file"));
+        }
+        let mut bests = PersonalBests::default();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (key, value) = match words.as_slice() {
+                [key, value] => (*key, *value),
+                _ => return Err(malformed(&format!("unrecognised line '{}'", line))),
+            };
+            let invalid = |_| malformed(&format!("invalid {}", key));
+            match key {
+                "score" => bests.score = value.parse().map_err(invalid)?,
+                "high_card" => bests.high_card = value.parse().map_err(invalid)?,
+                "moves" => bests.moves = value.parse().map_err(invalid)?,
+                "games" => bests.games = value.parse().map_err(invalid)?,
+                _ => return Err(malformed(&format!("unknown field '{}'", key))),
+            }
+        }
+        Ok(bests)
+    }
+}
+
+fn malformed(reason: &str) -> SaveError {
+    SaveError::Malformed(reason.to_string())
+}
+
+/// Reads the personal bests kept at `path`. Nobody has any bests before
+/// their first game, so a missing file isn't an error.
+pub fn load_bests(path: &Path) -> Result<PersonalBests, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => PersonalBests::from_bests_str(&contents),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(PersonalBests::default()),
+        Err(e) => Err(SaveError::Io(e)),
+    }
+}
+
+pub fn save_bests(bests: &PersonalBests, path: &Path) -> Result<(), SaveError> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    fs::write(path, bests.to_bests_string())?;
+    Ok(())
+}
+
+/// Where personal bests are kept between runs.
+pub fn default_bests_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("bests.txt"))
+}
+
+#[test]
+fn test_bests_string_round_trip() {
+    let bests = PersonalBests {
+        score: 12345,
+        high_card: 384,
+        moves: 250,
+        games: 7,
+    };
+    let loaded = PersonalBests::from_bests_str(&bests.to_bests_string()).unwrap();
+    assert_eq!(bests, loaded);
+}
+
+#[test]
+fn test_record_keeps_the_best() {
+    use moves::Direction;
+
+    let mut board = Board::with_seed(5);
+    for direction in Direction::ALL.iter().cycle().take(12) {
+        board.apply(*direction).unwrap();
+    }
+    let mut bests = PersonalBests {
+        score: u64::MAX,
+        high_card: 0,
+        moves: 0,
+        games: 2,
+    };
+    let new = bests.record(&board);
+    assert!(!new.score);
+    assert!(new.high_card);
+    assert!(new.moves);
+    assert_eq!(bests.score, u64::MAX);
+    assert_eq!(bests.high_card, board.get_high_card());
+    assert_eq!(bests.moves, board.get_moves().len());
+    assert_eq!(bests.games, 3);
+}
+
+#[test]
+fn test_missing_bests_file_is_empty() {
+    let path = std::env::temp_dir().join(format!("threes-no-bests-{}", std::process::id()));
+    assert_eq!(load_bests(&path).unwrap(), PersonalBests::default());
+}
+
+#[test]
+fn test_rejects_bad_bests() {
+    assert!(PersonalBests::from_bests_str("").is_err());
+    assert!(PersonalBests::from_bests_str("threes-bests 1\nscore lots\n").is_err());
+    assert!(PersonalBests::from_bests_str("threes-bests 1\nspeed 3\n").is_err());
+}
@@ -1,9 +1,16 @@
-use colored::*;
 use rand::distributions::{Distribution, Uniform};
-use rand::prelude::ThreadRng;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-
+use rand::{thread_rng, Rng};
+use std::cmp::Reverse;
+
+use deck::{generate_basic_stack, generate_bonus_stack};
+use error::ThreesError;
+use history::History;
+use moves::{Direction, Merge, MoveResult, Spawn, TileMove};
+use rng::GameRng;
+use rules::{handle_collisions, tile_score};
+use save::SavedGame;
+
+/// A game of Threes in progress: the 4x4 grid plus the decks new cards come from.
 #[derive(Debug)]
 pub struct Board {
     state: [[u32; 4]; 4],
@@ -11,16 +18,52 @@ pub struct Board {
     next_card: u32,
     basic_cards: Vec<u32>,
     bonus_cards: Vec<u32>,
-    rng: ThreadRng,
+    rng: GameRng,
+    history: History<Snapshot>,
+    /// The seed the game was started from, if known
+    seed: Option<u64>,
+    /// Every move that has been played since the game started
+    moves: Vec<Direction>,
+}
+
+/// Everything needed to put a board back exactly as it was, including where
+/// the random number generator had got to.
+#[derive(Clone, Debug)]
+struct Snapshot {
+    state: [[u32; 4]; 4],
+    high_card: u32,
+    next_card: u32,
+    basic_cards: Vec<u32>,
+    bonus_cards: Vec<u32>,
+    rng: GameRng,
+    moves: Vec<Direction>,
+}
+
+/// How many moves the frontends let players take back.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// A fresh basic deck has twelve cards, more than a new board deals.
+const FRESH_DECK: &str = "A fresh basic deck ran out of cards";
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Board {
+        Board::with_seed(thread_rng().gen())
+    }
+
+    /// Creates a board whose every random decision is driven by `seed`, so the
+    /// same seed and the same sequence of moves always play out identically.
+    pub fn with_seed(seed: u64) -> Board {
         let mut starting_state: [[u32; 4]; 4] = [[0; 4], [0; 4], [0; 4], [0; 4]];
 
-        let mut basic_stack = generate_basic_stack();
+        let mut rng = GameRng::new(seed);
+        let mut basic_stack = generate_basic_stack(&mut rng);
         let between = Uniform::new(0, 4);
-        let mut rng = thread_rng();
 
         // The starting board starts 9 cards off the basic stack at random places
         for _ in 0..9 {
@@ -29,7 +72,7 @@ impl Board {
                 let x = between.sample(&mut rng);
                 let y = between.sample(&mut rng);
                 if starting_state[x][y] == 0 {
-                    starting_state[x][y] = basic_stack.pop().unwrap();
+                    starting_state[x][y] = basic_stack.pop().expect(FRESH_DECK);
                     valid_place = true;
                 }
             }
@@ -38,133 +81,187 @@ impl Board {
         Board {
             state: starting_state,
             high_card: 3, // Can't be anything higher at this point
-            next_card: basic_stack.pop().unwrap(), // Next card is guaranteed to be basic
+            next_card: basic_stack.pop().expect(FRESH_DECK), // Next card is guaranteed to be basic
             basic_cards: basic_stack,
             bonus_cards: Vec::new(), // Guaranteed to be empty
             rng,
+            history: History::new(0),
+            seed: Some(seed),
+            moves: Vec::new(),
         }
     }
 
-    pub fn move_up(&mut self) -> bool {
-        let mut moved = false;
-        // Resolve by column from left to right
-        for col in 0..4 {
-            // Resolve from top to bottom, skipping final row
-            for row in 0..3 {
-                if let Some(x) = handle_collisions(self.state[row][col], self.state[row + 1][col]) {
-                    self.state[row + 1][col] = 0;
-                    self.state[row][col] = x;
-                    self.update_high_card(x);
-                    moved = true;
-                };
-            }
+    /// Pushes the whole board in `direction`, spawning the next card on the
+    /// opposite edge if anything moved.
+    pub fn apply(&mut self, direction: Direction) -> Result<MoveResult, ThreesError> {
+        let score_before = self.calculate_score();
+        let before = self.snapshot();
+        let (movements, merges) = slide(&mut self.state, direction);
+        for tile in movements
+            .iter()
+            .map(|m| m.value)
+            .chain(merges.iter().map(|m| m.value))
+        {
+            self.update_high_card(tile);
         }
 
+        let moved = !movements.is_empty();
+        let mut spawn = None;
         if moved {
-            // Spawn new tile in bottom row
+            self.history.record(before);
+            self.moves.push(direction);
+
+            // Spawn new tile somewhere along the edge being pushed away from
             let mut possible_locations = vec![];
-            for col in 0..4 {
-                if self.state[3][col] == 0 {
-                    possible_locations.push(col);
+            for line in 0..4 {
+                let (x, y) = direction.cell(line, 3);
+                if self.state[x][y] == 0 {
+                    possible_locations.push((x, y));
                 }
             }
             let between = Uniform::new(0, possible_locations.len());
 
-            let y = possible_locations[between.sample(&mut self.rng)];
-            self.spawn_next_tile(3, y);
-        }
-        moved
-    }
-
-    pub fn move_down(&mut self) -> bool {
-        let mut moved = false;
-        // Resolve by column from left to right
-        for col in 0..4 {
-            // Resolve from bottom to top, skipping first row
-            for row in (1..4).rev() {
-                if let Some(x) = handle_collisions(self.state[row][col], self.state[row - 1][col]) {
-                    self.state[row - 1][col] = 0;
-                    self.state[row][col] = x;
-                    self.update_high_card(x);
-                    moved = true;
-                };
-            }
+            let (x, y) = possible_locations[between.sample(&mut self.rng)];
+            spawn = Some(Spawn {
+                position: (x, y),
+                value: self.next_card,
+            });
+            self.spawn_next_tile(x, y)?;
         }
 
-        if moved {
-            // Spawn new tile in top row
-            let mut possible_locations = vec![];
-            for col in 0..4 {
-                if self.state[0][col] == 0 {
-                    possible_locations.push(col);
-                }
-            }
-            let between = Uniform::new(0, possible_locations.len());
-            let y = possible_locations[between.sample(&mut self.rng)];
-            self.spawn_next_tile(0, y);
+        Ok(MoveResult {
+            direction,
+            moved,
+            movements,
+            merges,
+            spawn,
+            next_card: self.next_card,
+            score_delta: self.calculate_score() - score_before,
+        })
+    }
+
+    /// The grid as it would be after pushing it in `direction`, before the
+    /// next card is spawned. The board itself, its decks and its random number
+    /// generator are left untouched.
+    pub fn preview(&self, direction: Direction) -> [[u32; 4]; 4] {
+        let mut state = self.state;
+        slide(&mut state, direction);
+        state
+    }
+
+    /// The directions that would move at least one tile, in the order of
+    /// `Direction::ALL`.
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        Direction::ALL
+            .iter()
+            .cloned()
+            .filter(|&direction| self.preview(direction) != self.state)
+            .collect()
+    }
+
+    pub fn move_up(&mut self) -> Result<bool, ThreesError> {
+        Ok(self.apply(Direction::Up)?.moved)
+    }
+
+    pub fn move_down(&mut self) -> Result<bool, ThreesError> {
+        Ok(self.apply(Direction::Down)?.moved)
+    }
+
+    pub fn move_left(&mut self) -> Result<bool, ThreesError> {
+        Ok(self.apply(Direction::Left)?.moved)
+    }
+
+    pub fn move_right(&mut self) -> Result<bool, ThreesError> {
+        Ok(self.apply(Direction::Right)?.moved)
+    }
+
+    /// Rebuilds a board from a saved game, ready to carry on exactly where it
+    /// left off. History isn't saved, so there is nothing to undo at first.
+    pub fn from_saved_game(game: SavedGame) -> Board {
+        Board {
+            state: game.state,
+            high_card: game.high_card,
+            next_card: game.next_card,
+            basic_cards: game.basic_cards,
+            bonus_cards: game.bonus_cards,
+            rng: GameRng::new(game.rng_state),
+            history: History::new(0),
+            seed: game.seed,
+            moves: game.moves,
         }
-        moved
-    }
-
-    pub fn move_left(&mut self) -> bool {
-        let mut moved = false;
-        // Resolve by column from top to bottom
-        for row in 0..4 {
-            // Resolve from left to right, skipping final column
-            for col in 0..3 {
-                if let Some(x) = handle_collisions(self.state[row][col], self.state[row][col + 1]) {
-                    self.state[row][col + 1] = 0;
-                    self.state[row][col] = x;
-                    self.update_high_card(x);
-                    moved = true;
-                };
-            }
+    }
+
+    pub fn to_saved_game(&self) -> SavedGame {
+        SavedGame {
+            state: self.state,
+            high_card: self.high_card,
+            next_card: self.next_card,
+            basic_cards: self.basic_cards.clone(),
+            bonus_cards: self.bonus_cards.clone(),
+            rng_state: self.rng.state(),
+            seed: self.seed,
+            moves: self.moves.clone(),
         }
+    }
 
-        if moved {
-            // Spawn new tile in right column
-            let mut possible_locations = vec![];
-            for row in 0..4 {
-                if self.state[row][3] == 0 {
-                    possible_locations.push(row);
-                }
+    /// Keeps up to `limit` previous positions so moves can be undone. A limit
+    /// of zero, the default, turns history off.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Takes back the last move. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let current = self.snapshot();
+        match self.history.undo(current) {
+            Some(previous) => {
+                self.restore(previous);
+                true
             }
-            let between = Uniform::new(0, possible_locations.len());
-            let x = possible_locations[between.sample(&mut self.rng)];
-            self.spawn_next_tile(x, 3);
+            None => false,
         }
-        moved
-    }
-
-    pub fn move_right(&mut self) -> bool {
-        let mut moved = false;
-        // Resolve by column from top to bottom
-        for row in 0..4 {
-            // Resolve from right to left, skipping first column
-            for col in (1..4).rev() {
-                if let Some(x) = handle_collisions(self.state[row][col], self.state[row][col - 1]) {
-                    self.state[row][col - 1] = 0;
-                    self.state[row][col] = x;
-                    self.update_high_card(x);
-                    moved = true;
-                };
+    }
+
+    /// Replays the last undone move. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let current = self.snapshot();
+        match self.history.redo(current) {
+            Some(next) => {
+                self.restore(next);
+                true
             }
+            None => false,
         }
+    }
 
-        if moved {
-            // Spawn new tile in left column
-            let mut possible_locations = vec![];
-            for row in 0..4 {
-                if self.state[row][0] == 0 {
-                    possible_locations.push(row);
-                }
-            }
-            let between = Uniform::new(0, possible_locations.len());
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
 
-            let x = possible_locations[between.sample(&mut self.rng)];
-            self.spawn_next_tile(x, 0);
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state,
+            high_card: self.high_card,
+            next_card: self.next_card,
+            basic_cards: self.basic_cards.clone(),
+            bonus_cards: self.bonus_cards.clone(),
+            rng: self.rng,
+            moves: self.moves.clone(),
         }
-        moved
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.state = snapshot.state;
+        self.high_card = snapshot.high_card;
+        self.next_card = snapshot.next_card;
+        self.basic_cards = snapshot.basic_cards;
+        self.bonus_cards = snapshot.bonus_cards;
+        self.rng = snapshot.rng;
+        self.moves = snapshot.moves;
     }
 
     fn update_high_card(&mut self, new_card: u32) {
@@ -173,130 +270,178 @@ impl Board {
         }
     }
 
-    fn spawn_next_tile(&mut self, x: usize, y: usize) {
+    fn spawn_next_tile(&mut self, x: usize, y: usize) -> Result<(), ThreesError> {
         self.state[x][y] = self.next_card;
 
         let between = Uniform::new(0, 21);
         let new_tile = if self.high_card >= 48 && between.sample(&mut self.rng) == 7 {
-            self.bonus_cards = generate_bonus_stack(self.high_card);
+            self.bonus_cards = generate_bonus_stack(self.high_card, &mut self.rng);
             self.bonus_cards.pop()
         } else {
             if self.basic_cards.is_empty() {
-                self.basic_cards = generate_basic_stack();
+                self.basic_cards = generate_basic_stack(&mut self.rng);
             }
             self.basic_cards.pop()
         };
-        self.next_card = new_tile.unwrap();
+        self.next_card = new_tile.ok_or(ThreesError::EmptyDeck)?;
+        Ok(())
     }
 
     pub fn get_board(&self) -> &[[u32; 4]] {
         &self.state
     }
 
+    /// The grid as text, one string per row, with each row's cells written
+    /// out as numbers between `separator`s.
+    pub fn grid_rows(&self, separator: &str) -> Vec<String> {
+        self.state
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(|tile| tile.to_string()).collect();
+                cells.join(separator)
+            })
+            .collect()
+    }
+
     pub fn get_next_card(&self) -> u32 {
         self.next_card
     }
 
-    pub fn print(&self) {
-        for row in &self.state {
-            println!();
-            for num in row {
-                if *num == 0 {
-                    print!(" \t")
-                } else {
-                    match num {
-                        1 => print!("{}\t", num.to_string().blue()),
-                        2 => print!("{}\t", num.to_string().red()),
-                        _ => print!("{}\t", num),
-                    }
-                }
-            }
+    /// Whether the next card came from the bonus deck. Real Threes only hints
+    /// at these with a "+" rather than showing the value.
+    pub fn next_card_is_bonus(&self) -> bool {
+        self.next_card > 3
+    }
+
+    /// The next card as real Threes shows it: its value if it's a 1, 2 or 3,
+    /// or "+" for anything from the bonus deck.
+    pub fn next_card_hint(&self) -> String {
+        if self.next_card_is_bonus() {
+            "+".to_string()
+        } else {
+            self.next_card.to_string()
         }
-        println!();
-        println!("\n\n")
     }
 
+    /// The highest tile made so far.
+    pub fn get_high_card(&self) -> u32 {
+        self.high_card
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Every move played so far, oldest first. Undone moves aren't included.
+    pub fn get_moves(&self) -> &[Direction] {
+        &self.moves
+    }
+
+    /// Whether any direction would move a tile, so the game isn't over yet.
     pub fn has_moves(&self) -> bool {
-        // Check for vertical moves
-        for col in 0..4 {
-            for row in 0..3 {
-                if handle_collisions(self.state[row][col], self.state[row + 1][col]).is_some() {
-                    return true;
-                }
-            }
-        }
-        // Check for horizontal moves
-        for row in 0..4 {
-            for col in 0..3 {
-                if handle_collisions(self.state[row][col], self.state[row][col + 1]).is_some() {
-                    return true;
-                }
-            }
-        }
-        false
+        !self.legal_moves().is_empty()
     }
 
     pub fn calculate_score(&self) -> u64 {
         let mut score = 0;
         for row in self.get_board().iter() {
             for tile in row.iter() {
-                // 1s and 2s are not worth points
-                if *tile == 1 || *tile == 2 {
-                    continue;
-                }
-                score += 3u64.pow(calculate_coefficient(*tile));
+                score += tile_score(*tile);
             }
         }
         score
     }
-}
 
-fn calculate_coefficient(x: u32) -> u32 {
-    // There's probably a much more elegant way of doing this
-    let mut y = x;
-    let mut coefficient = 1;
-    while y > 3 {
-        y /= 2;
-        coefficient += 1;
-    }
-    coefficient
-}
-
-fn handle_collisions(x: u32, y: u32) -> Option<u32> {
-    if x == 0 {
-        Some(y)
-    } else if (x == 1 && y == 2) | (y == 1 && x == 2) {
-        Some(3)
-    } else if x == y && x > 2 && y > 2 {
-        Some(x * 2)
-    } else {
-        None
+    /// The score split up by tile: each value on the board that's worth
+    /// points, highest first, with how many of it there are and what they're
+    /// worth together.
+    pub fn score_breakdown(&self) -> Vec<(u32, usize, u64)> {
+        let mut breakdown: Vec<(u32, usize, u64)> = vec![];
+        for &tile in self.state.iter().flat_map(|row| row.iter()) {
+            if tile_score(tile) == 0 {
+                continue;
+            }
+            match breakdown.iter_mut().find(|entry| entry.0 == tile) {
+                Some(entry) => {
+                    entry.1 += 1;
+                    entry.2 += tile_score(tile);
+                }
+                None => breakdown.push((tile, 1, tile_score(tile))),
+            }
+        }
+        breakdown.sort_by_key(|entry| Reverse(entry.0));
+        breakdown
     }
 }
 
-fn generate_basic_stack() -> Vec<u32> {
-    let mut stack = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3];
-    let mut rng = thread_rng();
-    stack.shuffle(&mut rng);
-    stack
-}
-
-fn generate_bonus_stack(high_card: u32) -> Vec<u32> {
-    let mut stack: Vec<u32> = Vec::new();
-    let mut next_value = high_card / 8;
-    while next_value > 3 {
-        stack.push(next_value);
-        next_value /= 2;
+/// Pushes every tile in `state` one cell in `direction` where it can go,
+/// returning which tiles moved and which of them merged.
+fn slide(state: &mut [[u32; 4]; 4], direction: Direction) -> (Vec<TileMove>, Vec<Merge>) {
+    let mut movements = vec![];
+    let mut merges = vec![];
+    for line in 0..4 {
+        // Resolve from the edge being pushed towards, skipping the far edge
+        for step in 0..3 {
+            let (x, y) = direction.cell(line, step);
+            let (next_x, next_y) = direction.cell(line, step + 1);
+            // Sliding an empty space along isn't a move
+            if state[next_x][next_y] == 0 {
+                continue;
+            }
+            let (target, moving) = (state[x][y], state[next_x][next_y]);
+            if let Some(tile) = handle_collisions(target, moving) {
+                state[next_x][next_y] = 0;
+                state[x][y] = tile;
+                movements.push(TileMove {
+                    from: (next_x, next_y),
+                    to: (x, y),
+                    value: moving,
+                });
+                if target != 0 {
+                    merges.push(Merge {
+                        from: (next_x, next_y),
+                        into: (x, y),
+                        value: tile,
+                    });
+                }
+            };
+        }
     }
-    let mut rng = thread_rng();
-    stack.shuffle(&mut rng);
-    stack
+    (movements, merges)
 }
 
 #[test]
 fn test_new_board_has_moves() {
     let board = Board::new();
-    assert_eq!(true, board.has_moves());
+    assert!(board.has_moves());
+}
+
+#[test]
+fn test_same_seed_same_game() {
+    let mut first = Board::with_seed(1234);
+    let mut second = Board::with_seed(1234);
+    assert_eq!(first.get_board(), second.get_board());
+    assert_eq!(first.get_next_card(), second.get_next_card());
+
+    type MoveFn = fn(&mut Board) -> Result<bool, ThreesError>;
+    let moves: [MoveFn; 4] = [
+        Board::move_up,
+        Board::move_left,
+        Board::move_down,
+        Board::move_right,
+    ];
+    for i in 0..200 {
+        if !first.has_moves() {
+            break;
+        }
+        let make_move = moves[i % 4];
+        assert_eq!(
+            make_move(&mut first).unwrap(),
+            make_move(&mut second).unwrap()
+        );
+        assert_eq!(first.get_board(), second.get_board());
+        assert_eq!(first.get_next_card(), second.get_next_card());
+    }
 }
 
 #[test]
@@ -308,14 +453,17 @@ fn test_half_played_has_moves() {
         [6, 12, 24, 6],
     ];
     let board = Board {
-        state: state,
+        state,
         high_card: 384,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
-    assert_eq!(true, board.has_moves());
+    assert!(board.has_moves());
 }
 
 #[test]
@@ -327,26 +475,32 @@ fn test_full_board_no_moves() {
         [6, 12, 24, 6],
     ];
     let board = Board {
-        state: state,
+        state,
         high_card: 384,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
-    assert_eq!(false, board.has_moves());
+    assert!(!board.has_moves());
 }
 
 #[test]
 fn test_calculate_score_zero() {
     let state: [[u32; 4]; 4] = [[1, 2, 1, 1], [2, 2, 1, 1], [2, 2, 1, 2], [2, 1, 2, 1]];
     let board = Board {
-        state: state,
+        state,
         high_card: 2,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
     assert_eq!(0, board.calculate_score());
 }
@@ -355,12 +509,15 @@ fn test_calculate_score_zero() {
 fn test_calculate_score_low() {
     let state: [[u32; 4]; 4] = [[3, 6, 3, 2], [2, 3, 6, 3], [3, 12, 1, 6], [12, 48, 6, 3]];
     let board = Board {
-        state: state,
+        state,
         high_card: 384,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
     assert_eq!(351, board.calculate_score());
 }
@@ -374,12 +531,15 @@ fn test_calculate_score_mid() {
         [768, 384, 96, 3],
     ];
     let board = Board {
-        state: state,
+        state,
         high_card: 384,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
     assert_eq!(27432, board.calculate_score());
 }
@@ -393,93 +553,291 @@ fn test_calculate_score_high() {
         [1536, 768, 384, 192],
     ];
     let board = Board {
-        state: state,
+        state,
         high_card: 1536,
         next_card: 3,
         basic_cards: vec![],
         bonus_cards: vec![],
-        rng: thread_rng(),
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
     };
     assert_eq!(88836, board.calculate_score());
 }
 
 #[test]
-fn test_collision_x_zero() {
-    let (x, y) = (0, 3);
-    let result = handle_collisions(x, y);
-    assert_eq!(result, Some(y));
+fn test_score_breakdown() {
+    let state: [[u32; 4]; 4] = [[3, 1, 2, 0], [6, 3, 0, 0], [0, 0, 0, 0], [0, 0, 0, 384]];
+    let board = test_board(state);
+    let breakdown = board.score_breakdown();
+    assert_eq!(breakdown, vec![(384, 1, 6561), (6, 1, 9), (3, 2, 6)]);
+    let total: u64 = breakdown.iter().map(|entry| entry.2).sum();
+    assert_eq!(total, board.calculate_score());
+}
+
+#[cfg(test)]
+fn test_board(state: [[u32; 4]; 4]) -> Board {
+    Board {
+        state,
+        high_card: 3,
+        next_card: 3,
+        basic_cards: vec![],
+        bonus_cards: vec![],
+        rng: GameRng::new(0),
+        history: History::new(0),
+        seed: None,
+        moves: vec![],
+    }
+}
+
+#[test]
+fn test_apply_each_direction() {
+    let state: [[u32; 4]; 4] = [[0, 1, 0, 0], [0, 2, 0, 0], [3, 0, 3, 0], [0, 0, 0, 0]];
+
+    let mut board = test_board(state);
+    assert!(board.apply(Direction::Up).unwrap().moved);
+    assert_eq!(board.get_board()[0], [0, 3, 0, 0]);
+    assert_eq!(board.get_board()[1], [3, 0, 3, 0]);
+
+    let mut board = test_board(state);
+    assert!(board.apply(Direction::Down).unwrap().moved);
+    assert_eq!(board.get_board()[1], [0, 1, 0, 0]);
+    assert_eq!(board.get_board()[2], [0, 2, 0, 0]);
+    assert_eq!(board.get_board()[3], [3, 0, 3, 0]);
+
+    let mut board = test_board(state);
+    assert!(board.apply(Direction::Left).unwrap().moved);
+    assert_eq!(board.get_board()[0][0], 1);
+    assert_eq!(board.get_board()[1][0], 2);
+    assert_eq!(&board.get_board()[2][..3], &[3, 3, 0]);
+
+    let mut board = test_board(state);
+    assert!(board.apply(Direction::Right).unwrap().moved);
+    assert_eq!(board.get_board()[0][2], 1);
+    assert_eq!(board.get_board()[1][2], 2);
+    assert_eq!(&board.get_board()[2][1..], &[3, 0, 3]);
+}
+
+#[test]
+fn test_apply_spawns_on_far_edge() {
+    let state: [[u32; 4]; 4] = [[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 6, 0]];
+    let mut board = test_board(state);
+    assert!(board.apply(Direction::Up).unwrap().moved);
+    assert_eq!(board.get_board()[2][2], 6);
+    assert_eq!(board.get_board()[3].iter().filter(|&&x| x == 3).count(), 1);
+}
+
+#[test]
+fn test_apply_blocked_does_nothing() {
+    let state: [[u32; 4]; 4] = [[3, 0, 0, 0], [1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    let mut board = test_board(state);
+    assert!(!board.apply(Direction::Left).unwrap().moved);
+    assert_eq!(board.get_board(), &state[..]);
+    assert_eq!(board.get_next_card(), 3);
 }
 
 #[test]
-fn test_collision_one_two() {
-    let (x, y) = (1, 2);
-    assert_eq!(handle_collisions(x, y), Some(3));
-    assert_eq!(handle_collisions(y, x), Some(3));
+fn test_apply_reports_what_happened() {
+    let state: [[u32; 4]; 4] = [[1, 2, 0, 0], [3, 3, 0, 0], [0, 0, 0, 0], [0, 0, 0, 6]];
+    let mut board = test_board(state);
+    let result = board.apply(Direction::Left).unwrap();
+
+    assert!(result.moved);
+    assert_eq!(result.direction, Direction::Left);
+    assert_eq!(
+        result.merges,
+        vec![
+            Merge {
+                from: (0, 1),
+                into: (0, 0),
+                value: 3,
+            },
+            Merge {
+                from: (1, 1),
+                into: (1, 0),
+                value: 6,
+            },
+        ]
+    );
+    assert_eq!(result.movements.len(), 3);
+    assert_eq!(
+        result.movements[2],
+        TileMove {
+            from: (3, 3),
+            to: (3, 2),
+            value: 6,
+        }
+    );
+    assert!(result.movements.iter().all(|m| m.vector() == (0, -1)));
+
+    let spawn = result.spawn.unwrap();
+    assert_eq!(spawn.value, 3);
+    assert_eq!(spawn.position.1, 3);
+    assert_eq!(board.get_board()[spawn.position.0][3], 3);
+    assert_eq!(result.next_card, board.get_next_card());
+
+    // 1 + 2 makes a 3 worth 3, 3 + 3 makes a 6 worth 9 from 6, and the spawned 3
+    assert_eq!(result.score_delta, 3 + 3 + 3);
 }
 
 #[test]
-fn test_collision_of_like_values() {
-    assert_eq!(handle_collisions(3, 3), Some(6));
-    assert_eq!(handle_collisions(6, 6), Some(12));
-    assert_eq!(handle_collisions(12, 12), Some(24));
-    assert_eq!(handle_collisions(24, 24), Some(48));
-    assert_eq!(handle_collisions(48, 48), Some(96));
-    assert_eq!(handle_collisions(96, 96), Some(192));
-    assert_eq!(handle_collisions(192, 192), Some(384));
+fn test_preview_matches_move_without_spawning() {
+    let board = Board::with_seed(21);
+    let rng = board.rng;
+    let next_card = board.get_next_card();
+    for direction in Direction::ALL.iter() {
+        let preview = board.preview(*direction);
+        assert_eq!(board.rng, rng);
+        assert_eq!(board.get_next_card(), next_card);
+        assert!(board.get_moves().is_empty());
+
+        let mut played = Board::with_seed(21);
+        let result = played.apply(*direction).unwrap();
+        let mut expected = [[0; 4]; 4];
+        expected.copy_from_slice(played.get_board());
+        if let Some(spawn) = result.spawn {
+            expected[spawn.position.0][spawn.position.1] = 0;
+        }
+        assert_eq!(preview, expected);
+    }
 }
 
 #[test]
-fn test_collision_fails() {
-    assert_eq!(handle_collisions(1, 1), None);
-    assert_eq!(handle_collisions(2, 2), None);
-    assert_eq!(handle_collisions(3, 1), None);
-    assert_eq!(handle_collisions(3, 2), None);
-    assert_eq!(handle_collisions(1, 3), None);
-    assert_eq!(handle_collisions(2, 3), None);
+fn test_preview_of_blocked_move_is_unchanged() {
+    let state: [[u32; 4]; 4] = [[3, 0, 0, 0], [1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    let board = test_board(state);
+    assert_eq!(board.preview(Direction::Up), state);
 }
 
 #[test]
-fn test_basic_stack() {
-    let mut stack = generate_basic_stack();
-    assert_eq!(12, stack.len());
-    stack.sort();
-    assert_eq!(stack[0], 1);
-    assert_eq!(stack[4], 2);
-    assert_eq!(stack[8], 3);
+fn test_legal_moves_leave_out_blocked_directions() {
+    let state: [[u32; 4]; 4] = [[3, 0, 0, 0], [1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    let board = test_board(state);
+    assert_eq!(board.legal_moves(), vec![Direction::Down, Direction::Right]);
 }
 
 #[test]
-fn test_bonus_stack_empty() {
-    let stack = generate_bonus_stack(24);
-    assert_eq!(0, stack.len());
+fn test_blocked_move_reports_nothing() {
+    let state: [[u32; 4]; 4] = [[3, 0, 0, 0], [1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    let mut board = test_board(state);
+    let result = board.apply(Direction::Up).unwrap();
+    assert!(!result.moved);
+    assert!(result.movements.is_empty());
+    assert!(result.merges.is_empty());
+    assert_eq!(result.spawn, None);
+    assert_eq!(result.score_delta, 0);
+}
+
+#[test]
+fn test_undo_restores_everything() {
+    let mut board = Board::with_seed(99);
+    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+    let mut played = Board::with_seed(99);
+    assert!(!board.undo());
+
+    let mut moves = 0;
+    for direction in Direction::ALL.iter().cycle().take(40) {
+        if board.apply(*direction).unwrap().moved {
+            played.apply(*direction).unwrap();
+            moves += 1;
+        }
+    }
+    for _ in 0..moves {
+        assert!(board.undo());
+    }
+    assert!(!board.undo());
+    let fresh = Board::with_seed(99);
+    assert_eq!(board.get_board(), fresh.get_board());
+    assert_eq!(board.get_next_card(), fresh.get_next_card());
+
+    // Redoing everything lands back on the same game, and carries on identically
+    while board.redo() {}
+    assert_eq!(board.get_board(), played.get_board());
+    for direction in Direction::ALL.iter() {
+        assert_eq!(
+            board.apply(*direction).unwrap(),
+            played.apply(*direction).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_undo_then_move_clears_redo() {
+    let state: [[u32; 4]; 4] = [[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 3, 0, 0]];
+    let mut board = test_board(state);
+    board.set_history_limit(5);
+    assert!(board.apply(Direction::Up).unwrap().moved);
+    assert!(board.undo());
+    assert_eq!(board.get_board(), &state[..]);
+    assert!(board.can_redo());
+    assert!(board.apply(Direction::Right).unwrap().moved);
+    assert!(!board.can_redo());
+    assert!(!board.redo());
+}
+
+#[test]
+fn test_history_off_by_default() {
+    let mut board = Board::with_seed(5);
+    for direction in Direction::ALL.iter() {
+        board.apply(*direction).unwrap();
+    }
+    assert!(!board.can_undo());
+    assert!(!board.undo());
+}
+
+#[test]
+fn test_moves_follow_undo_and_redo() {
+    let mut board = Board::with_seed(21);
+    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+    assert_eq!(board.get_seed(), Some(21));
+    let mut played = vec![];
+    for direction in Direction::ALL.iter() {
+        if board.apply(*direction).unwrap().moved {
+            played.push(*direction);
+        }
+    }
+    assert_eq!(board.get_moves(), &played[..]);
+    board.undo();
+    assert_eq!(board.get_moves(), &played[..played.len() - 1]);
+    board.redo();
+    assert_eq!(board.get_moves(), &played[..]);
 }
 
 #[test]
-fn test_bonus_stack_96() {
-    let mut stack = generate_bonus_stack(96);
-    assert_eq!(2, stack.len());
-    stack.sort();
-    assert_eq!(stack[0], 6);
-    assert_eq!(stack[1], 12);
+fn test_high_card_and_bonus_hint() {
+    let state: [[u32; 4]; 4] = [[48, 48, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    let mut board = test_board(state);
+    assert_eq!(board.get_high_card(), 3);
+    assert!(!board.next_card_is_bonus());
+    assert_eq!(board.next_card_hint(), board.get_next_card().to_string());
+    board.apply(Direction::Left).unwrap();
+    assert_eq!(board.get_high_card(), 96);
+
+    board.next_card = 12;
+    assert!(board.next_card_is_bonus());
+    assert_eq!(board.next_card_hint(), "+");
 }
 
 #[test]
-fn test_bonus_stack_192() {
-    let mut stack = generate_bonus_stack(192);
-    assert_eq!(3, stack.len());
-    stack.sort();
-    assert_eq!(stack[0], 6);
-    assert_eq!(stack[1], 12);
-    assert_eq!(stack[2], 24);
+fn test_grid_rows() {
+    let state: [[u32; 4]; 4] = [[1, 2, 3, 0], [0, 0, 0, 0], [6, 12, 24, 48], [0, 0, 0, 384]];
+    let board = test_board(state);
+    assert_eq!(
+        board.grid_rows(","),
+        vec!["1,2,3,0", "0,0,0,0", "6,12,24,48", "0,0,0,384"]
+    );
+    assert_eq!(board.grid_rows(" ")[3], "0 0 0 384");
 }
 
 #[test]
-fn test_bonus_stack_384() {
-    let mut stack = generate_bonus_stack(384);
-    assert_eq!(4, stack.len());
-    stack.sort();
-    assert_eq!(stack[0], 6);
-    assert_eq!(stack[1], 12);
-    assert_eq!(stack[2], 24);
-    assert_eq!(stack[3], 48);
+fn test_empty_bottom_right_cell_still_has_moves() {
+    // Seed 1 after 31 moves. Nothing can merge, but the last cell is empty
+    let mut game = Board::with_seed(1).to_saved_game();
+    game.state = [[3, 2, 6, 2], [1, 6, 12, 6], [6, 24, 2, 2], [3, 1, 3, 0]];
+    game.high_card = 24;
+    let mut board = Board::from_saved_game(game);
+    assert_eq!(board.legal_moves(), vec![Direction::Down, Direction::Right]);
+    assert!(board.has_moves());
+    assert!(board.apply(Direction::Down).unwrap().moved);
 }
@@ -0,0 +1,334 @@
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::{GameControllerSubsystem, Sdl};
+use std::collections::HashMap;
+
+use threes::Direction;
+
+/// How far the left stick has to be pushed, out of 32767, to make a move
+const STICK_PUSH: i16 = 16_000;
+/// How close to the centre the stick has to come back before it can make
+/// another move. Keeping this well below `STICK_PUSH` stops a stick resting
+/// near the threshold from jittering out a stream of moves.
+const STICK_RELEASE: i16 = 8_000;
+
+/// What a controller asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pad {
+    Move(Direction),
+    Undo,
+    Redo,
+    /// Starts another game
+    NewGame,
+    /// Quits, or leaves the current menu
+    Quit,
+    /// Picks the highlighted menu item
+    Select,
+    Pause,
+    /// Leaves the current menu
+    Back,
+}
+
+/// Where a controller's left stick is, and whether it's already made a move
+/// since it was last centred.
+#[derive(Clone, Copy, Debug, Default)]
+struct Stick {
+    x: i16,
+    y: i16,
+    pushed: bool,
+}
+
+/// Every game controller plugged in, opened as they're connected and dropped
+/// as they're removed.
+pub struct Controllers {
+    subsystem: Option<GameControllerSubsystem>,
+    open: HashMap<i32, GameController>,
+    sticks: HashMap<i32, Stick>,
+}
+
+impl Controllers {
+    /// Controllers are optional: if SDL can't provide them the game just
+    /// doesn't hear from any.
+    pub fn new(sdl: &Sdl) -> Controllers {
+        let subsystem = match sdl.game_controller() {
+            Ok(subsystem) => Some(subsystem),
+            Err(e) => {
+                eprintln!("Game controllers unavailable: {}", e);
+                None
+            }
+        };
+        Controllers {
+            subsystem,
+            open: HashMap::new(),
+            sticks: HashMap::new(),
+        }
+    }
+
+    /// Keeps track of controllers coming and going, and turns their input
+    /// into what the player meant by it.
+    ///
+    /// D-pad directions and left stick pushes move, or move around menus.
+    /// The face buttons are X to undo, Y to start a new game, B to quit and A
+    /// to pick menu items. The right shoulder redoes, start pauses and back
+    /// leaves menus.
+    pub fn handle_event(&mut self, event: &Event) -> Option<Pad> {
+        match *event {
+            // SDL also announces controllers that were already plugged in
+            // when it started, so this covers those too
+            Event::ControllerDeviceAdded { which, .. } => {
+                let subsystem = self.subsystem.as_ref()?;
+                match subsystem.open(which as u32) {
+                    Ok(controller) => {
+                        self.open.insert(controller.instance_id(), controller);
+                    }
+                    Err(e) => eprintln!("Couldn't open game controller: {}", e),
+                }
+                None
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.open.remove(&which);
+                self.sticks.remove(&which);
+                None
+            }
+            Event::ControllerButtonDown { button, .. } => button_command(button),
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let stick = self.sticks.entry(which).or_default();
+                match axis {
+                    Axis::LeftX => stick.x = value,
+                    Axis::LeftY => stick.y = value,
+                    _ => return None,
+                }
+                stick.update().map(Pad::Move)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn button_command(button: Button) -> Option<Pad> {
+    match button {
+        Button::DPadUp => Some(Pad::Move(Direction::Up)),
+        Button::DPadDown => Some(Pad::Move(Direction::Down)),
+        Button::DPadLeft => Some(Pad::Move(Direction::Left)),
+        Button::DPadRight => Some(Pad::Move(Direction::Right)),
+        Button::X => Some(Pad::Undo),
+        Button::Y => Some(Pad::NewGame),
+        Button::B => Some(Pad::Quit),
+        Button::A => Some(Pad::Select),
+        Button::RightShoulder => Some(Pad::Redo),
+        Button::Start => Some(Pad::Pause),
+        Button::Back => Some(Pad::Back),
+        _ => None,
+    }
+}
+
+impl Stick {
+    /// The move made by the stick's latest position, if it's just been pushed
+    /// out of the deadzone. Snaps to whichever axis is pushed furthest.
+    fn update(&mut self) -> Option<Direction> {
+        let (x, y) = (i32::from(self.x), i32::from(self.y));
+        let furthest = x.abs().max(y.abs());
+        if self.pushed {
+            if furthest < i32::from(STICK_RELEASE) {
+                self.pushed = false;
+            }
+            return None;
+        }
+        if furthest < i32::from(STICK_PUSH) {
+            return None;
+        }
+        self.pushed = true;
+        Some(if x.abs() > y.abs() {
+            if x > 0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if y > 0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
+
+/// Controllers with no SDL behind them, for feeding events to by hand.
+#[cfg(test)]
+fn test_controllers() -> Controllers {
+    Controllers {
+        subsystem: None,
+        open: HashMap::new(),
+        sticks: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+fn axis_event(which: i32, axis: Axis, value: i16) -> Event {
+    Event::ControllerAxisMotion {
+        timestamp: 0,
+        which,
+        axis,
+        value,
+    }
+}
+
+#[test]
+fn test_stick_moves_once_per_push() {
+    let mut stick = Stick {
+        x: STICK_PUSH - 1,
+        ..Stick::default()
+    };
+    assert_eq!(stick.update(), None);
+    stick.x = STICK_PUSH;
+    assert_eq!(stick.update(), Some(Direction::Right));
+    // Held, or eased back but not past the release point, it stays quiet
+    stick.x = i16::MAX;
+    assert_eq!(stick.update(), None);
+    stick.x = STICK_RELEASE;
+    assert_eq!(stick.update(), None);
+    stick.x = STICK_PUSH;
+    assert_eq!(stick.update(), None);
+    // Coming back near the centre re-arms it
+    stick.x = STICK_RELEASE - 1;
+    assert_eq!(stick.update(), None);
+    stick.x = STICK_PUSH;
+    assert_eq!(stick.update(), Some(Direction::Right));
+}
+
+#[test]
+fn test_stick_snaps_to_the_furthest_axis() {
+    let cases = [
+        ((20_000, 5_000), Direction::Right),
+        ((-20_000, 19_000), Direction::Left),
+        ((3_000, 17_000), Direction::Down),
+        ((-16_000, -30_000), Direction::Up),
+    ];
+    for &((x, y), direction) in cases.iter() {
+        let mut stick = Stick {
+            x,
+            y,
+            pushed: false,
+        };
+        assert_eq!(stick.update(), Some(direction), "at ({}, {})", x, y);
+    }
+}
+
+#[test]
+fn test_axis_events_move_with_the_left_stick_only() {
+    let mut controllers = test_controllers();
+    assert_eq!(
+        controllers.handle_event(&axis_event(0, Axis::RightX, i16::MAX)),
+        None
+    );
+    assert_eq!(
+        controllers.handle_event(&axis_event(0, Axis::LeftY, i16::MIN)),
+        Some(Pad::Move(Direction::Up))
+    );
+    // Each controller's stick is tracked separately
+    assert_eq!(
+        controllers.handle_event(&axis_event(1, Axis::LeftY, i16::MIN)),
+        Some(Pad::Move(Direction::Up))
+    );
+    assert_eq!(
+        controllers.handle_event(&axis_event(0, Axis::LeftY, i16::MIN)),
+        None
+    );
+}
+
+#[test]
+fn test_buttons() {
+    let mut controllers = test_controllers();
+    let cases = [
+        (Button::DPadUp, Some(Pad::Move(Direction::Up))),
+        (Button::DPadDown, Some(Pad::Move(Direction::Down))),
+        (Button::DPadLeft, Some(Pad::Move(Direction::Left))),
+        (Button::DPadRight, Some(Pad::Move(Direction::Right))),
+        (Button::X, Some(Pad::Undo)),
+        (Button::Y, Some(Pad::NewGame)),
+        (Button::B, Some(Pad::Quit)),
+        (Button::A, Some(Pad::Select)),
+        (Button::RightShoulder, Some(Pad::Redo)),
+        (Button::Start, Some(Pad::Pause)),
+        (Button::Back, Some(Pad::Back)),
+        (Button::LeftShoulder, None),
+    ];
+    for &(button, pad) in cases.iter() {
+        let event = Event::ControllerButtonDown {
+            timestamp: 0,
+            which: 0,
+            button,
+        };
+        assert_eq!(controllers.handle_event(&event), pad, "{:?}", button);
+    }
+}
+
+#[test]
+fn test_unplugging_forgets_the_stick() {
+    let mut controllers = test_controllers();
+    let plugged_in = Event::ControllerDeviceAdded {
+        timestamp: 0,
+        which: 0,
+    };
+    // Without SDL's controller support there's nothing to open
+    assert_eq!(controllers.handle_event(&plugged_in), None);
+    assert!(controllers.open.is_empty());
+
+    let push = axis_event(3, Axis::LeftX, i16::MIN);
+    assert_eq!(
+        controllers.handle_event(&push),
+        Some(Pad::Move(Direction::Left))
+    );
+    let unplugged = Event::ControllerDeviceRemoved {
+        timestamp: 0,
+        which: 3,
+    };
+    assert_eq!(controllers.handle_event(&unplugged), None);
+    assert!(controllers.sticks.is_empty());
+    // A controller plugged in as the same instance starts afresh
+    assert_eq!(
+        controllers.handle_event(&push),
+        Some(Pad::Move(Direction::Left))
+    );
+}
+
+#[test]
+fn test_a_game_played_with_a_controller() {
+    let mut controllers = test_controllers();
+    let button = |button| Event::ControllerButtonDown {
+        timestamp: 0,
+        which: 2,
+        button,
+    };
+    let events = [
+        button(Button::DPadRight),
+        axis_event(2, Axis::LeftY, i16::MAX),
+        // Letting the button and the stick go does nothing
+        Event::ControllerButtonUp {
+            timestamp: 0,
+            which: 2,
+            button: Button::DPadRight,
+        },
+        axis_event(2, Axis::LeftY, 0),
+        button(Button::X),
+        button(Button::RightShoulder),
+        button(Button::Y),
+        button(Button::B),
+    ];
+    let pads: Vec<Pad> = events
+        .iter()
+        .filter_map(|event| controllers.handle_event(event))
+        .collect();
+    assert_eq!(
+        pads,
+        vec![
+            Pad::Move(Direction::Right),
+            Pad::Move(Direction::Down),
+            Pad::Undo,
+            Pad::Redo,
+            Pad::NewGame,
+            Pad::Quit,
+        ]
+    );
+}
@@ -0,0 +1,71 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+#[cfg(test)]
+use rng::GameRng;
+
+/// A freshly shuffled basic deck: four each of 1, 2 and 3.
+pub fn generate_basic_stack<R: Rng>(rng: &mut R) -> Vec<u32> {
+    let mut stack = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3];
+    stack.shuffle(rng);
+    stack
+}
+
+/// A shuffled bonus deck for the given high card, holding every value from
+/// `high_card / 8` down to 6.
+pub fn generate_bonus_stack<R: Rng>(high_card: u32, rng: &mut R) -> Vec<u32> {
+    let mut stack: Vec<u32> = Vec::new();
+    let mut next_value = high_card / 8;
+    while next_value > 3 {
+        stack.push(next_value);
+        next_value /= 2;
+    }
+    stack.shuffle(rng);
+    stack
+}
+
+#[test]
+fn test_basic_stack() {
+    let mut stack = generate_basic_stack(&mut GameRng::new(0));
+    assert_eq!(12, stack.len());
+    stack.sort();
+    assert_eq!(stack[0], 1);
+    assert_eq!(stack[4], 2);
+    assert_eq!(stack[8], 3);
+}
+
+#[test]
+fn test_bonus_stack_empty() {
+    let stack = generate_bonus_stack(24, &mut GameRng::new(0));
+    assert_eq!(0, stack.len());
+}
+
+#[test]
+fn test_bonus_stack_96() {
+    let mut stack = generate_bonus_stack(96, &mut GameRng::new(0));
+    assert_eq!(2, stack.len());
+    stack.sort();
+    assert_eq!(stack[0], 6);
+    assert_eq!(stack[1], 12);
+}
+
+#[test]
+fn test_bonus_stack_192() {
+    let mut stack = generate_bonus_stack(192, &mut GameRng::new(0));
+    assert_eq!(3, stack.len());
+    stack.sort();
+    assert_eq!(stack[0], 6);
+    assert_eq!(stack[1], 12);
+    assert_eq!(stack[2], 24);
+}
+
+#[test]
+fn test_bonus_stack_384() {
+    let mut stack = generate_bonus_stack(384, &mut GameRng::new(0));
+    assert_eq!(4, stack.len());
+    stack.sort();
+    assert_eq!(stack[0], 6);
+    assert_eq!(stack[1], 12);
+    assert_eq!(stack[2], 24);
+    assert_eq!(stack[3], 48);
+}
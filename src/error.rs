@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use replay::Desync;
+use save::SaveError;
+
+/// Everything that can go wrong while playing, grouped by what went wrong so
+/// the binary can exit with a different code for each.
+#[derive(Debug)]
+pub enum ThreesError {
+    /// The command line didn't make sense
+    Usage(String),
+    /// Reading from or writing to the terminal failed
+    Io(io::Error),
+    /// The save, replay or personal bests file at the path couldn't be read
+    /// or written
+    Save(PathBuf, SaveError),
+    /// A replay didn't play back the way it was recorded
+    Desync(Desync),
+    /// Moves piped in couldn't be understood. The message says where.
+    Input(String),
+    /// The window, or something it draws with, couldn't be set up. The
+    /// message says what was being attempted.
+    Gui(String),
+    /// The server couldn't listen for connections. The message says what was
+    /// being attempted.
+    Network(String, io::Error),
+    /// A deck ran out of cards, which the rules should never allow
+    EmptyDeck,
+}
+
+impl ThreesError {
+    /// The process exit code for this kind of failure.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            ThreesError::Usage(_) => 2,
+            ThreesError::Io(_) => 3,
+            ThreesError::Save(..) => 4,
+            ThreesError::Desync(_) => 5,
+            ThreesError::Gui(_) => 6,
+            ThreesError::Input(_) => 7,
+            ThreesError::Network(..) => 8,
+            ThreesError::EmptyDeck => 70,
+        }
+    }
+}
+
+impl fmt::Display for ThreesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThreesError::Usage(ref reason) => write!(f, "{}", reason),
+            ThreesError::Io(ref e) => write!(f, "couldn't talk to the terminal: {}", e),
+            ThreesError::Save(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            ThreesError::Desync(ref e) => write!(f, "{}", e),
+            ThreesError::Gui(ref reason) => write!(f, "{}", reason),
+            ThreesError::Input(ref reason) => write!(f, "{}", reason),
+            ThreesError::Network(ref attempt, ref e) => write!(f, "couldn't {}: {}", attempt, e),
+            ThreesError::EmptyDeck => write!(f, "ran out of cards to deal"),
+        }
+    }
+}
+
+impl Error for ThreesError {}
+
+impl From<io::Error> for ThreesError {
+    fn from(e: io::Error) -> ThreesError {
+        ThreesError::Io(e)
+    }
+}
+
+impl From<Desync> for ThreesError {
+    fn from(e: Desync) -> ThreesError {
+        ThreesError::Desync(e)
+    }
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let errors = [
+        ThreesError::Usage(String::new()),
+        ThreesError::Io(io::Error::other("gone")),
+        ThreesError::Save(PathBuf::new(), SaveError::UnsupportedVersion(9)),
+        ThreesError::Desync(Desync { move_number: 1 }),
+        ThreesError::Gui(String::new()),
+        ThreesError::Input(String::new()),
+        ThreesError::Network(String::new(), io::Error::other("refused")),
+        ThreesError::EmptyDeck,
+    ];
+    let mut codes: Vec<i32> = errors.iter().map(ThreesError::exit_code).collect();
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), errors.len());
+    assert!(!codes.contains(&0) && !codes.contains(&1));
+}
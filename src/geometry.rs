@@ -0,0 +1,150 @@
+use sdl2::video::{FullscreenType, Window};
+use std::fs;
+use std::path::PathBuf;
+
+use threes::save::data_dir;
+
+/// Where the window was and how big it was, so the next run can put it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The position and size are those from before going fullscreen
+    pub fullscreen: bool,
+}
+
+impl WindowGeometry {
+    /// The window's current position and size.
+    pub fn of(window: &Window) -> WindowGeometry {
+        let (x, y) = window.position();
+        let (width, height) = window.size();
+        WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+            fullscreen: window.fullscreen_state() != FullscreenType::Off,
+        }
+    }
+
+    /// The geometry remembered from last time, if there is any. A missing or
+    /// unreadable file just means the window opens at its default size.
+    pub fn load() -> Option<WindowGeometry> {
+        let contents = fs::read_to_string(geometry_path()?).ok()?;
+        WindowGeometry::from_geometry_str(&contents)
+    }
+
+    pub fn save(&self) {
+        let path = match geometry_path() {
+            Some(path) => path,
+            None => return,
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(&path, self.to_geometry_string()) {
+            eprintln!(
+                "Couldn't remember the window size in {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fn to_geometry_string(self) -> String {
+        format!(
+            "position {} {}\nsize {} {}\nfullscreen {}\n",
+            self.x, self.y, self.width, self.height, self.fullscreen as u8
+        )
+    }
+
+    fn from_geometry_str(contents: &str) -> Option<WindowGeometry> {
+        let mut position = None;
+        let mut size = None;
+        let mut fullscreen = false;
+        for line in contents.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["position", x, y] => position = Some((x.parse().ok()?, y.parse().ok()?)),
+                ["size", width, height] => size = Some((width.parse().ok()?, height.parse().ok()?)),
+                ["fullscreen", value] => fullscreen = *value == "1",
+                _ => {}
+            }
+        }
+        let (x, y) = position?;
+        let (width, height) = size?;
+        Some(WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+            fullscreen,
+        })
+    }
+}
+
+fn geometry_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("window.txt"))
+}
+
+#[test]
+fn test_geometry_round_trip() {
+    let geometries = [
+        WindowGeometry {
+            x: -20,
+            y: 40,
+            width: 800,
+            height: 600,
+            fullscreen: true,
+        },
+        WindowGeometry {
+            x: 0,
+            y: 0,
+            width: 420,
+            height: 1000,
+            fullscreen: false,
+        },
+    ];
+    for geometry in geometries.iter() {
+        let text = geometry.to_geometry_string();
+        assert_eq!(WindowGeometry::from_geometry_str(&text), Some(*geometry));
+    }
+}
+
+#[test]
+fn test_geometry_ignores_unknown_lines() {
+    let geometry = WindowGeometry::from_geometry_str("size 640 480\ncolour blue\nposition 5 6\n");
+    assert_eq!(
+        geometry,
+        Some(WindowGeometry {
+            x: 5,
+            y: 6,
+            width: 640,
+            height: 480,
+            fullscreen: false,
+        })
+    );
+}
+
+#[test]
+fn test_malformed_geometry_is_ignored() {
+    let broken = [
+        "",
+        "position 10 20\n",
+        "position 10 20\nsize 800",
+        "position 10 20\nsi",
+        "position ten 20\nsize 800 600\n",
+        "position 10 20\nsize -800 600\n",
+        "position 10 20\nsize 800 600 400\n",
+    ];
+    for contents in broken.iter() {
+        assert_eq!(
+            WindowGeometry::from_geometry_str(contents),
+            None,
+            "{:?}",
+            contents
+        );
+    }
+}
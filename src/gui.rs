@@ -1,216 +1,1189 @@
 use sdl2;
-use sdl2::event::Event;
-use sdl2::image::LoadTexture;
-use sdl2::keyboard::Keycode;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, LSHIFTMOD, RSHIFTMOD};
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::EventPump;
-use std::collections::HashMap;
+use sdl2::render::{BlendMode, Canvas, TextureCreator};
+use sdl2::ttf::Font;
+use sdl2::video::{FullscreenType, Window, WindowContext};
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use board::Board;
-
-use std::{thread, time};
+use animation::Animation;
+use assets::Assets;
+use controller::{Controllers, Pad};
+use geometry::WindowGeometry;
+use layout::{Layout, BOARD_SIZE, HUD_HEIGHT};
+use menu::{menu_for, Area, Menu, MenuAction, MenuEvent, MenuInput, Screen, Transition};
+use persist::{autosave, record_replay};
+use swipe::drag_direction;
+use threes::bests::default_bests_path;
+use threes::{
+    load_bests, save_bests, Board, Direction, MoveResult, NewBests, PersonalBests, Recorder,
+    ReplayPlayer, ThreesError, DEFAULT_HISTORY_LIMIT,
+};
+use tiles::{TileRenderer, BACKGROUND};
 
 struct ThreesWindow {
     board: Board,
     canvas: Canvas<Window>,
+    save_path: Option<PathBuf>,
+    recorder: Recorder,
+    record_path: Option<PathBuf>,
+    options: GuiOptions,
+    /// The move currently being animated, if any
+    animation: Option<Animation>,
+    /// Input that arrived while a move was still animating
+    pending: VecDeque<Action>,
+    /// The move being previewed while shift and an arrow are held, or while
+    /// the mouse or a finger is dragged
+    peek: Option<Direction>,
+    /// Where the current mouse drag or swipe started
+    drag_start: Option<(i32, i32)>,
+    /// The finger doing the swiping, so a second finger can't hijack it
+    swipe_finger: Option<i64>,
+    controllers: Controllers,
+    /// Where the window is, or was before going fullscreen
+    windowed: WindowGeometry,
+    /// Whether anything has changed since the window was last drawn
+    dirty: bool,
+    screen: Screen,
+    /// The buttons on the current screen
+    menu: Menu<MenuAction>,
+    bests: PersonalBests,
+    /// Which bests the last finished game beat
+    new_bests: NewBests,
 }
 
-enum PostGameOption {
-    Quit,
-    Restart,
-    DisplayScore,
+/// How the window should look and behave.
+#[derive(Clone, Debug)]
+pub struct GuiOptions {
+    /// Draw tiles with the PNG artwork instead of drawing them
+    pub png_tiles: bool,
+    /// The font and artwork, built in or from a skin
+    pub assets: Assets,
+    /// How long each move takes to animate. Zero makes moves instant.
+    pub animation: Duration,
+    /// How far, in pixels, a drag or swipe has to go before it counts as a move
+    pub swipe_threshold: u32,
 }
 
-impl<'a> ThreesWindow {
-    fn new() -> ThreesWindow {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
-
-        let window = video_subsystem
-            .window("Threes.rs", 420, 420)
-            .position_centered()
-            .build()
-            .unwrap();
+pub const DEFAULT_ANIMATION_MS: u64 = 150;
+pub const DEFAULT_SWIPE_THRESHOLD: u32 = 30;
 
-        let mut canvas = window
-            .into_canvas()
-            .target_texture()
-            .present_vsync()
-            .build()
-            .unwrap();
+impl Default for GuiOptions {
+    fn default() -> GuiOptions {
+        GuiOptions {
+            png_tiles: false,
+            assets: Assets::embedded(),
+            animation: Duration::from_millis(DEFAULT_ANIMATION_MS),
+            swipe_threshold: DEFAULT_SWIPE_THRESHOLD,
+        }
+    }
+}
 
-        canvas.clear();
+/// Something the player asked for, carried out once the board is free.
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Move(Direction),
+    Undo,
+    Redo,
+}
 
-        ThreesWindow {
-            board: Board::new(),
+impl ThreesWindow {
+    fn new(
+        board: Board,
+        save_path: Option<PathBuf>,
+        record_path: Option<PathBuf>,
+        options: GuiOptions,
+    ) -> Result<ThreesWindow, ThreesError> {
+        let (canvas, windowed) = create_canvas()?;
+        let bests = match default_bests_path().map(|path| load_bests(&path)) {
+            Some(Ok(bests)) => bests,
+            Some(Err(e)) => {
+                eprintln!("Ignoring unreadable personal bests: {}", e);
+                PersonalBests::default()
+            }
+            None => PersonalBests::default(),
+        };
+        Ok(ThreesWindow {
+            windowed,
+            dirty: true,
+            screen: Screen::Title,
+            menu: menu_for(Screen::Title, !board.get_moves().is_empty()),
+            bests,
+            new_bests: NewBests::default(),
+            options,
+            recorder: Recorder::new(&board),
+            board,
+            controllers: Controllers::new(&canvas.window().subsystem().sdl()),
             canvas,
-        }
+            save_path,
+            record_path,
+            animation: None,
+            pending: VecDeque::new(),
+            peek: None,
+            drag_start: None,
+            swipe_finger: None,
+        })
     }
 
-    fn play(&mut self) {
+    fn play(&mut self) -> Result<(), ThreesError> {
         let texture_creator = self.canvas.texture_creator();
-        let ttf_context = sdl2::ttf::init().unwrap();
-        let font = ttf_context
-            .load_font("./resources/font/Raleway-Black.ttf", 64)
-            .unwrap();
-
-        // TODO: Work out how to move this into a method without fighting the lifetime
-        let mut assets = HashMap::new();
-        assets.insert(
-            0,
-            texture_creator
-                .load_texture("./resources/zero.png")
-                .unwrap(),
-        );
-        assets.insert(
-            1,
-            texture_creator.load_texture("./resources/one.png").unwrap(),
-        );
-        assets.insert(
-            2,
-            texture_creator.load_texture("./resources/two.png").unwrap(),
-        );
-        assets.insert(
-            3,
-            texture_creator
-                .load_texture("./resources/three.png")
-                .unwrap(),
-        );
-        assets.insert(
-            6,
-            texture_creator.load_texture("./resources/six.png").unwrap(),
-        );
-        assets.insert(
-            12,
-            texture_creator
-                .load_texture("./resources/twelve.png")
-                .unwrap(),
-        );
-        assets.insert(
-            24,
-            texture_creator
-                .load_texture("./resources/twenty_four.png")
-                .unwrap(),
-        );
-        assets.insert(
-            48,
-            texture_creator
-                .load_texture("./resources/forty_eight.png")
-                .unwrap(),
-        );
-        assets.insert(
-            96,
-            texture_creator
-                .load_texture("./resources/ninety_six.png")
-                .unwrap(),
-        );
-        assets.insert(
-            192,
-            texture_creator
-                .load_texture("./resources/one_nine_two.png")
-                .unwrap(),
-        );
-        assets.insert(
-            384,
-            texture_creator
-                .load_texture("./resources/three_eight_four.png")
-                .unwrap(),
-        );
-        assets.insert(
-            762,
-            texture_creator
-                .load_texture("./resources/seven_six_two.png")
-                .unwrap(),
-        );
-        assets.insert(
-            1524,
-            texture_creator
-                .load_texture("./resources/one_five_two_four.png")
-                .unwrap(),
-        );
-        assets.insert(
-            3048,
-            texture_creator
-                .load_texture("./resources/three_zero_four_eight.png")
-                .unwrap(),
-        );
-
-        let mut event_pump = self.canvas.window().subsystem().sdl().event_pump().unwrap();
-        'game: loop {
-            if !self.board.has_moves() || !self.handle_input(&mut event_pump) {
-                // if true {
-                self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-                self.canvas.clear();
-
-                let score_str = format!("Score: {}", self.board.calculate_score());
-                let score_surface = font
-                    .render(&score_str)
-                    .blended_wrapped(Color::RGB(0, 0, 0), 500)
-                    .unwrap();
-                let score_texture = texture_creator
-                    .create_texture_from_surface(&score_surface)
-                    .unwrap();
-
-                let description_str = "Press r to start a new game, or q/ESC to quit";
-                let desc_surface = font
-                    .render(&description_str)
-                    .blended_wrapped(Color::RGB(0, 0, 0), 500)
-                    .unwrap();
-                let desc_texture = texture_creator
-                    .create_texture_from_surface(&desc_surface)
-                    .unwrap();
-
-                'end: loop {
-                    match self.handle_end_input(&mut event_pump) {
-                        PostGameOption::Quit => {
-                            break 'game;
-                        }
-                        PostGameOption::DisplayScore => {}
-                        PostGameOption::Restart => {
-                            self.board = Board::new();
-                            break 'end;
-                        }
+        let ttf_context = sdl2::ttf::init().map_err(gui_error("start SDL_ttf"))?;
+        let assets = self.options.assets.clone();
+        let font = assets
+            .load_font(&ttf_context, 64)
+            .map_err(gui_error("load the font"))?;
+
+        let mut tiles =
+            TileRenderer::new(&texture_creator, &font, &assets, self.options.png_tiles)?;
+
+        let mut event_pump = self
+            .canvas
+            .window()
+            .subsystem()
+            .sdl()
+            .event_pump()
+            .map_err(gui_error("read events"))?;
+        loop {
+            // Sleep until something happens, unless a move is still playing out
+            let mut quit = false;
+            if !self.busy() && !self.dirty {
+                quit = !self.handle_event(event_pump.wait_event())?;
+            }
+            while !quit {
+                match event_pump.poll_event() {
+                    Some(event) => quit = !self.handle_event(event)?,
+                    None => break,
+                }
+            }
+            if quit {
+                break;
+            }
+
+            if self.screen == Screen::Playing {
+                self.advance()?;
+                if !self.busy() && !self.board.has_moves() {
+                    self.finish_game();
+                }
+            }
+
+            if self.dirty || self.animation.is_some() {
+                self.draw(&texture_creator, &font, &mut tiles)?;
+                self.dirty = false;
+            }
+        }
+        remember_geometry(&self.canvas, self.windowed);
+        Ok(())
+    }
+
+    /// Whether a move is still animating or waiting to be played, in which
+    /// case the loop has to keep running rather than wait for input.
+    fn busy(&self) -> bool {
+        self.animation.is_some() || !self.pending.is_empty()
+    }
+
+    /// Reacts to a single event. Returns false if the player asked to quit.
+    fn handle_event(&mut self, event: Event) -> Result<bool, ThreesError> {
+        if handle_window_event(&mut self.canvas, &mut self.windowed, &event) {
+            self.dirty = true;
+            return Ok(true);
+        }
+        match event {
+            // Closing the window quits straight away, as the game is saved
+            Event::Quit { .. } => Ok(false),
+            // Covers being uncovered or resized, which need a redraw
+            Event::Window { .. } => {
+                self.dirty = true;
+                Ok(true)
+            }
+            _ if self.screen == Screen::Playing => {
+                self.handle_play_event(event);
+                Ok(true)
+            }
+            _ => self.handle_menu_event(event),
+        }
+    }
+
+    /// Arrow keys and WASD move, shift and an arrow previews a move, U or Z
+    /// undoes, Y redoes, ESC or P pauses and Q asks to quit. A controller's
+    /// new game button pauses with New game highlighted, so a game in
+    /// progress isn't thrown away by one press.
+    fn handle_play_event(&mut self, event: Event) {
+        if let Some(pad) = self.controllers.handle_event(&event) {
+            match pad {
+                Pad::Move(direction) => self.pending.push_back(Action::Move(direction)),
+                Pad::Undo => self.pending.push_back(Action::Undo),
+                Pad::Redo => self.pending.push_back(Action::Redo),
+                Pad::Pause | Pad::Back => self.show(Screen::Paused),
+                Pad::NewGame => {
+                    self.show(Screen::Paused);
+                    self.menu.highlight(MenuAction::NewGame);
+                }
+                Pad::Quit => self.show(Screen::ConfirmQuit),
+                Pad::Select => {}
+            }
+            return;
+        }
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } if self.peek.is_some() => {
+                self.cancel_peek();
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::P),
+                ..
+            } => {
+                self.show(Screen::Paused);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Q),
+                ..
+            } => {
+                self.show(Screen::ConfirmQuit);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::U),
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Z),
+                ..
+            } => {
+                self.pending.push_back(Action::Undo);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Y),
+                ..
+            } => {
+                self.pending.push_back(Action::Redo);
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                ..
+            } => {
+                if let Some(direction) = key_direction(keycode) {
+                    if keymod.intersects(LSHIFTMOD | RSHIFTMOD) {
+                        self.peek = Some(direction);
+                        self.dirty = true;
+                    } else {
+                        self.pending.push_back(Action::Move(direction));
+                    }
+                }
+            }
+            Event::KeyUp {
+                keycode: Some(Keycode::LShift),
+                ..
+            }
+            | Event::KeyUp {
+                keycode: Some(Keycode::RShift),
+                ..
+            } => {
+                self.cancel_peek();
+            }
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } if self.peek.is_some() && key_direction(keycode) == self.peek => {
+                self.commit_peek();
+            }
+            // SDL also reports touches as mouse events, which are
+            // handled as fingers below instead
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                which,
+                x,
+                y,
+                ..
+            } if which != TOUCH_MOUSE_ID => {
+                self.drag_start = Some((x, y));
+            }
+            Event::MouseMotion { which, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                self.drag_to((x, y));
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                which,
+                ..
+            } if which != TOUCH_MOUSE_ID => {
+                self.drag_start = None;
+                self.commit_peek();
+            }
+            Event::FingerDown {
+                finger_id, x, y, ..
+            } if self.swipe_finger.is_none() => {
+                self.swipe_finger = Some(finger_id);
+                self.drag_start = Some(self.touch_position(x, y));
+            }
+            Event::FingerMotion {
+                finger_id, x, y, ..
+            } if self.swipe_finger == Some(finger_id) => {
+                let position = self.touch_position(x, y);
+                self.drag_to(position);
+            }
+            Event::FingerUp { finger_id, .. } if self.swipe_finger == Some(finger_id) => {
+                self.swipe_finger = None;
+                self.drag_start = None;
+                self.commit_peek();
+            }
+            _ => {}
+        }
+    }
+
+    /// Works the menu on the title, pause, quit and game over screens.
+    /// Returns false if the player chose to quit.
+    fn handle_menu_event(&mut self, event: Event) -> Result<bool, ThreesError> {
+        if let Some(input) = menu_input(&event, pixels_per_point(&self.canvas)?) {
+            let layout = canvas_layout(&self.canvas)?;
+            let to_pixels = |area: Area| {
+                let rect = layout.rect(area.x, area.y, area.width, area.height);
+                Area::new(rect.x(), rect.y(), rect.width(), rect.height())
+            };
+            match self.menu.handle_input(input, to_pixels) {
+                MenuEvent::Chosen(action) => return Ok(self.choose(action)),
+                MenuEvent::Moved => {
+                    self.dirty = true;
+                    return Ok(true);
+                }
+                MenuEvent::Ignored => {}
+            }
+        }
+        if let Some(pad) = self.controllers.handle_event(&event) {
+            match pad {
+                Pad::Move(Direction::Up) | Pad::Move(Direction::Left) => self.menu.previous(),
+                Pad::Move(_) => self.menu.next(),
+                Pad::Select | Pad::Pause => {
+                    if let Some(action) = self.menu.chosen() {
+                        return Ok(self.choose(action));
                     }
-                    self.canvas
-                        .copy(&score_texture, None, Rect::new(50, 50, 350, 100))
-                        .unwrap();
-                    self.canvas
-                        .copy(&desc_texture, None, Rect::new(50, 200, 350, 200))
-                        .unwrap();
-                    self.canvas.present();
-
-                    thread::sleep(time::Duration::from_millis(10));
                 }
+                Pad::Back | Pad::Quit => return Ok(self.back()),
+                Pad::NewGame => {
+                    if self.menu.highlight(MenuAction::NewGame) {
+                        return Ok(self.choose(MenuAction::NewGame));
+                    }
+                }
+                Pad::Undo | Pad::Redo => {}
+            }
+            self.dirty = true;
+            return Ok(true);
+        }
+        Ok(match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => self.back(),
+            Event::KeyDown {
+                keycode: Some(Keycode::P),
+                ..
+            } if self.screen == Screen::Paused => self.choose(MenuAction::Play),
+            Event::KeyDown {
+                keycode: Some(Keycode::R),
+                ..
+            } if self.screen == Screen::GameOver => self.choose(MenuAction::NewGame),
+            Event::KeyDown {
+                keycode: Some(Keycode::Q),
+                ..
+            } => self.choose(MenuAction::Quit),
+            _ => true,
+        })
+    }
+
+    /// Carries out a menu choice. Returns false if it was to quit.
+    fn choose(&mut self, action: MenuAction) -> bool {
+        let transition = self.screen.choose(action);
+        self.go(transition)
+    }
+
+    /// Backs out of the current screen. Returns false if that quits.
+    fn back(&mut self) -> bool {
+        let transition = self.screen.back();
+        self.go(transition)
+    }
+
+    /// Moves to another screen, or quits by returning false.
+    fn go(&mut self, transition: Transition) -> bool {
+        match transition {
+            Transition::Show(screen) => self.show(screen),
+            Transition::NewGame => {
+                // A game abandoned part way through still gets its replay
+                if self.screen != Screen::GameOver && !self.board.get_moves().is_empty() {
+                    record_replay(&self.board, &self.recorder, &self.record_path);
+                }
+                self.board = new_board();
+                self.recorder = Recorder::new(&self.board);
+                self.autosave();
+                self.show(Screen::Playing);
+            }
+            Transition::Quit => return false,
+        }
+        true
+    }
+
+    fn show(&mut self, screen: Screen) {
+        // Finish or drop any moves in flight, so nothing changes behind a menu
+        self.animation = None;
+        self.pending.clear();
+        self.cancel_peek();
+        self.screen = screen;
+        self.menu = menu_for(screen, !self.board.get_moves().is_empty());
+        self.dirty = true;
+    }
+
+    /// Records the finished game's replay and personal bests, then shows how
+    /// it went.
+    fn finish_game(&mut self) {
+        record_replay(&self.board, &self.recorder, &self.record_path);
+        self.new_bests = self.bests.record(&self.board);
+        if let Some(path) = default_bests_path() {
+            if let Err(e) = save_bests(&self.bests, &path) {
+                eprintln!("Couldn't save personal bests to {}: {}", path.display(), e);
+            }
+        }
+        self.show(Screen::GameOver);
+    }
+
+    fn draw(
+        &mut self,
+        texture_creator: &TextureCreator<WindowContext>,
+        font: &Font,
+        tiles: &mut TileRenderer,
+    ) -> Result<(), ThreesError> {
+        let layout = canvas_layout(&self.canvas)?;
+        let canvas = &mut self.canvas;
+        let centre = BOARD_SIZE as i32 / 2;
+        match self.screen {
+            Screen::Title => {
+                canvas.set_draw_color(BACKGROUND);
+                canvas.clear();
+                draw_text_centred(
+                    canvas,
+                    &layout,
+                    texture_creator,
+                    font,
+                    "Threes",
+                    centre,
+                    110,
+                    90,
+                )?;
+            }
+            Screen::Playing | Screen::Paused | Screen::ConfirmQuit => {
+                draw_board(canvas, &layout, tiles, &self.board, self.animation.as_ref())?;
+                if let (Some(direction), None) = (self.peek, self.animation.as_ref()) {
+                    draw_ghost(canvas, &layout, tiles, &self.board, direction)?;
+                }
+                draw_hud(canvas, &layout, texture_creator, font, tiles, &self.board)?;
+            }
+            Screen::GameOver => draw_game_over(
+                canvas,
+                &layout,
+                texture_creator,
+                font,
+                tiles,
+                &self.board,
+                &self.bests,
+                self.new_bests,
+            )?,
+        }
+        match self.screen {
+            Screen::Paused => {
+                fade(canvas, MENU_FADE)?;
+                draw_text_centred(
+                    canvas,
+                    &layout,
+                    texture_creator,
+                    font,
+                    "Paused",
+                    centre,
+                    150,
+                    56,
+                )?;
+            }
+            Screen::ConfirmQuit => {
+                fade(canvas, MENU_FADE)?;
+                draw_text_centred(
+                    canvas,
+                    &layout,
+                    texture_creator,
+                    font,
+                    "Quit?",
+                    centre,
+                    130,
+                    56,
+                )?;
+                let saved = "Your game is saved for next time";
+                draw_text_centred(
+                    canvas,
+                    &layout,
+                    texture_creator,
+                    font,
+                    saved,
+                    centre,
+                    200,
+                    20,
+                )?;
+            }
+            _ => {}
+        }
+        draw_menu(canvas, &layout, texture_creator, font, &self.menu)?;
+        canvas.present();
+        Ok(())
+    }
+
+    /// Plays the move being previewed, if there is one.
+    fn commit_peek(&mut self) {
+        if let Some(direction) = self.peek.take() {
+            self.pending.push_back(Action::Move(direction));
+            self.dirty = true;
+        }
+    }
+
+    fn cancel_peek(&mut self) {
+        self.dirty |= self.peek.is_some();
+        self.peek = None;
+        self.drag_start = None;
+        self.swipe_finger = None;
+    }
+
+    /// Previews the move the current drag points to, if it's gone far enough.
+    fn drag_to(&mut self, position: (i32, i32)) {
+        if let Some(start) = self.drag_start {
+            let peek = drag_direction(start, position, self.options.swipe_threshold);
+            self.dirty |= peek != self.peek;
+            self.peek = peek;
+        }
+    }
+
+    /// Converts a touch position, given as a fraction of the window, to pixels.
+    fn touch_position(&self, x: f32, y: f32) -> (i32, i32) {
+        let (width, height) = self.canvas.window().size();
+        ((x * width as f32) as i32, (y * height as f32) as i32)
+    }
+
+    /// Finishes the current animation once it has run its course, then
+    /// carries out queued input until something changes the board.
+    fn advance(&mut self) -> Result<(), ThreesError> {
+        if self.animation.as_ref().is_some_and(|a| a.is_finished()) {
+            self.animation = None;
+            self.dirty = true;
+        }
+        while self.animation.is_none() {
+            let changed = match self.pending.pop_front() {
+                Some(Action::Move(direction)) => {
+                    let before = board_grid(&self.board);
+                    let result = self.board.apply(direction)?;
+                    let moved = result.moved;
+                    if moved {
+                        self.animation = animate(before, result, &self.options);
+                    }
+                    moved
+                }
+                Some(Action::Undo) => self.board.undo(),
+                Some(Action::Redo) => self.board.redo(),
+                None => return Ok(()),
+            };
+            if changed {
+                self.dirty = true;
+                self.autosave();
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn autosave(&mut self) {
+        self.recorder.sync(&self.board);
+        autosave(&self.board, &self.save_path);
+    }
+}
+
+/// Opens the window where it was last time, or centred at its natural size
+/// the first time. Returns the canvas along with the window's geometry when
+/// not fullscreen.
+fn create_canvas() -> Result<(Canvas<Window>, WindowGeometry), ThreesError> {
+    let sdl_context = sdl2::init().map_err(gui_error("start SDL"))?;
+    let video_subsystem = sdl_context
+        .video()
+        .map_err(gui_error("start SDL's video subsystem"))?;
+
+    let remembered = WindowGeometry::load();
+    let mut builder = match remembered {
+        Some(geometry) => {
+            let mut builder = video_subsystem.window("Threes.rs", geometry.width, geometry.height);
+            builder.position(geometry.x, geometry.y);
+            builder
+        }
+        None => {
+            let mut builder =
+                video_subsystem.window("Threes.rs", BOARD_SIZE, BOARD_SIZE + HUD_HEIGHT);
+            builder.position_centered();
+            builder
+        }
+    };
+    let mut window = builder
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .map_err(gui_error("open the window"))?;
+    window
+        .set_minimum_size(BOARD_SIZE / 2, (BOARD_SIZE + HUD_HEIGHT) / 2)
+        .map_err(gui_error("set the window's minimum size"))?;
+    let windowed = WindowGeometry::of(&window);
+    if remembered.is_some_and(|geometry| geometry.fullscreen) {
+        set_fullscreen(&mut window, true);
+    }
+
+    let mut canvas = window
+        .into_canvas()
+        .target_texture()
+        .present_vsync()
+        .build()
+        .map_err(gui_error("create a renderer for the window"))?;
+
+    canvas.clear();
+    Ok((canvas, windowed))
+}
+
+/// Wraps an SDL error up with what was being attempted when it happened.
+pub fn gui_error<E: fmt::Display>(attempt: &'static str) -> impl FnOnce(E) -> ThreesError {
+    move |e| ThreesError::Gui(format!("couldn't {}: {}", attempt, e))
+}
+
+/// Handles the events every window reacts to the same way: F11 toggles
+/// fullscreen, and moving or resizing the window is kept track of so it can
+/// be put back next time. Returns whether `event` was one of these.
+fn handle_window_event(
+    canvas: &mut Canvas<Window>,
+    windowed: &mut WindowGeometry,
+    event: &Event,
+) -> bool {
+    match *event {
+        Event::KeyDown {
+            keycode: Some(Keycode::F11),
+            ..
+        } => {
+            let window = canvas.window_mut();
+            let fullscreen = window.fullscreen_state() == FullscreenType::Off;
+            set_fullscreen(window, fullscreen);
+            true
+        }
+        Event::Window {
+            win_event: WindowEvent::Moved(..),
+            ..
+        }
+        | Event::Window {
+            win_event: WindowEvent::Resized(..),
+            ..
+        } => {
+            let geometry = WindowGeometry::of(canvas.window());
+            if !geometry.fullscreen {
+                *windowed = geometry;
             }
-            // Redraw the board onto the screen
-            let state = self.board.get_board();
-            for (row_num, row) in state.iter().enumerate() {
+            true
+        }
+        _ => false,
+    }
+}
+
+fn set_fullscreen(window: &mut Window, fullscreen: bool) {
+    let mode = if fullscreen {
+        FullscreenType::Desktop
+    } else {
+        FullscreenType::Off
+    };
+    if let Err(e) = window.set_fullscreen(mode) {
+        eprintln!("Couldn't change fullscreen mode: {}", e);
+    }
+}
+
+/// Saves where the window is for next time. Fullscreen windows are remembered
+/// as being fullscreen, along with where to go when they leave it.
+fn remember_geometry(canvas: &Canvas<Window>, windowed: WindowGeometry) {
+    let fullscreen = canvas.window().fullscreen_state() != FullscreenType::Off;
+    WindowGeometry {
+        fullscreen,
+        ..windowed
+    }
+    .save();
+}
+
+/// How many drawable pixels there are to each point the mouse is measured in,
+/// across and down. More than one on high DPI displays.
+fn pixels_per_point(canvas: &Canvas<Window>) -> Result<(f32, f32), ThreesError> {
+    let (points_x, points_y) = canvas.window().size();
+    let (pixels_x, pixels_y) = canvas
+        .output_size()
+        .map_err(gui_error("get the window's size"))?;
+    Ok((
+        pixels_x as f32 / points_x.max(1) as f32,
+        pixels_y as f32 / points_y.max(1) as f32,
+    ))
+}
+
+/// What a key or mouse event does to a menu. Arrow keys, WASD and tab move
+/// the highlight, and enter or space picks the highlighted item. Mouse
+/// positions are in points, which `pixels_per_point` converts to drawable
+/// pixels.
+fn menu_input(event: &Event, pixels_per_point: (f32, f32)) -> Option<MenuInput> {
+    let to_pixels = |x: i32, y: i32| {
+        (
+            (x as f32 * pixels_per_point.0) as i32,
+            (y as f32 * pixels_per_point.1) as i32,
+        )
+    };
+    match *event {
+        Event::KeyDown {
+            keycode: Some(keycode),
+            ..
+        } => match keycode {
+            Keycode::Up | Keycode::W | Keycode::Left | Keycode::A => Some(MenuInput::Previous),
+            Keycode::Down | Keycode::S | Keycode::Right | Keycode::D | Keycode::Tab => {
+                Some(MenuInput::Next)
+            }
+            Keycode::Return | Keycode::KpEnter | Keycode::Space => Some(MenuInput::Pick),
+            _ => None,
+        },
+        Event::MouseMotion { x, y, .. } => {
+            let (x, y) = to_pixels(x, y);
+            Some(MenuInput::Hover(x, y))
+        }
+        Event::MouseButtonUp {
+            mouse_btn: MouseButton::Left,
+            x,
+            y,
+            ..
+        } => {
+            let (x, y) = to_pixels(x, y);
+            Some(MenuInput::Click(x, y))
+        }
+        _ => None,
+    }
+}
+
+/// The layout for the canvas's drawable area as it is right now.
+fn canvas_layout(canvas: &Canvas<Window>) -> Result<Layout, ThreesError> {
+    let (width, height) = canvas
+        .output_size()
+        .map_err(gui_error("get the window's size"))?;
+    Ok(Layout::new(width, height))
+}
+
+/// The mouse SDL pretends touches come from
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+/// How strongly the board is faded while previewing a move, out of 255
+const GHOST_FADE: u8 = 110;
+/// How strongly the game is faded behind the pause and quit menus
+const MENU_FADE: u8 = 215;
+const MENU_BUTTON: Color = Color {
+    r: 228,
+    g: 222,
+    b: 212,
+    a: 255,
+};
+const MENU_SELECTED: Color = Color {
+    r: 255,
+    g: 204,
+    b: 102,
+    a: 255,
+};
+/// How many lines of the score breakdown fit beside the final board
+const BREAKDOWN_LINES: usize = 8;
+const TEXT_COLOUR: Color = Color {
+    r: 119,
+    g: 110,
+    b: 101,
+    a: 255,
+};
+
+/// Draws the board, or the current frame of `animation` if a move is still
+/// playing out.
+fn draw_board(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    tiles: &mut TileRenderer,
+    board: &Board,
+    animation: Option<&Animation>,
+) -> Result<(), ThreesError> {
+    canvas.set_draw_color(BACKGROUND);
+    canvas.clear();
+
+    let animation = match animation {
+        Some(animation) => animation,
+        None => {
+            for (row_num, row) in board.get_board().iter().enumerate() {
                 for (col_num, col) in row.iter().enumerate() {
-                    self.canvas
-                        .copy(
-                            assets.get(col).unwrap(),
-                            None,
-                            Rect::new(
-                                10 + (100 * col_num) as i32,
-                                10 + (100 * row_num) as i32,
-                                100,
-                                100,
-                            ),
-                        )
-                        .expect("Failed to draw rect");
+                    tiles.draw(
+                        canvas,
+                        *col,
+                        layout.cell(row_num as f32, col_num as f32, 1.0),
+                    )?;
                 }
             }
+            return Ok(());
+        }
+    };
 
-            self.canvas.present();
-            thread::sleep(time::Duration::from_millis(10));
+    for row in 0..4 {
+        for col in 0..4 {
+            tiles.draw(canvas, 0, layout.cell(row as f32, col as f32, 1.0))?;
         }
     }
+    // Keep cards sliding in from beyond the edge off the HUD
+    canvas.set_clip_rect(layout.board());
+    let drawn = animation.sprites().iter().try_for_each(|sprite| {
+        let rect = layout.cell(sprite.row, sprite.col, sprite.scale);
+        tiles.draw(canvas, sprite.value, rect)
+    });
+    canvas.set_clip_rect(None);
+    drawn
+}
+
+/// Shows the grid as it would be after moving in `direction`, faded out so
+/// it reads as a preview rather than the real board.
+fn draw_ghost(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    tiles: &mut TileRenderer,
+    board: &Board,
+    direction: Direction,
+) -> Result<(), ThreesError> {
+    for (row_num, row) in board.preview(direction).iter().enumerate() {
+        for (col_num, col) in row.iter().enumerate() {
+            tiles.draw(
+                canvas,
+                *col,
+                layout.cell(row_num as f32, col_num as f32, 1.0),
+            )?;
+        }
+    }
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(
+        BACKGROUND.r,
+        BACKGROUND.g,
+        BACKGROUND.b,
+        GHOST_FADE,
+    ));
+    let faded = canvas
+        .fill_rect(layout.board())
+        .map_err(gui_error("fade the preview"));
+    canvas.set_blend_mode(BlendMode::None);
+    faded
+}
+
+fn board_grid(board: &Board) -> [[u32; 4]; 4] {
+    let mut grid = [[0; 4]; 4];
+    for (row, tiles) in grid.iter_mut().zip(board.get_board().iter()) {
+        row.copy_from_slice(&tiles[..]);
+    }
+    grid
+}
+
+/// An animation of `result`, or `None` when animations are turned off.
+fn animate(before: [[u32; 4]; 4], result: MoveResult, options: &GuiOptions) -> Option<Animation> {
+    if options.animation == Duration::from_millis(0) {
+        return None;
+    }
+    Some(Animation::new(before, result, options.animation))
+}
+
+/// Draws the strip above the board: a preview of the next card, then the
+/// score, the number of moves made and the best tile so far.
+fn draw_hud(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    tiles: &mut TileRenderer,
+    board: &Board,
+) -> Result<(), ThreesError> {
+    draw_text(canvas, layout, texture_creator, font, "Next", 26, 8, 22)?;
+    let preview = layout.rect(15, 32, 70, 70);
+    if board.next_card_is_bonus() {
+        tiles.draw_bonus_hint(canvas, preview)?;
+    } else {
+        tiles.draw(canvas, board.get_next_card(), preview)?;
+    }
+
+    let score = format!("Score: {}", board.calculate_score());
+    draw_text(canvas, layout, texture_creator, font, &score, 120, 10, 32)?;
+    let moves = format!("Moves: {}", board.get_moves().len());
+    draw_text(canvas, layout, texture_creator, font, &moves, 120, 48, 24)?;
+    let best = format!("Best tile: {}", board.get_high_card());
+    draw_text(canvas, layout, texture_creator, font, &best, 120, 78, 24)
+}
+
+/// The game over screen: the final board, what the tiles on it scored, and
+/// how the game compares with the best so far.
+#[allow(clippy::too_many_arguments)]
+fn draw_game_over(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    tiles: &mut TileRenderer,
+    board: &Board,
+    bests: &PersonalBests,
+    new: NewBests,
+) -> Result<(), ThreesError> {
+    canvas.set_draw_color(BACKGROUND);
+    canvas.clear();
+    let centre = BOARD_SIZE as i32 / 2;
+    draw_text_centred(
+        canvas,
+        layout,
+        texture_creator,
+        font,
+        "Game over",
+        centre,
+        10,
+        40,
+    )?;
+    let score = format!("Score: {}{}", board.calculate_score(), new_best(new.score));
+    draw_text_centred(
+        canvas,
+        layout,
+        texture_creator,
+        font,
+        &score,
+        centre,
+        56,
+        28,
+    )?;
+
+    for (row_num, row) in board.get_board().iter().enumerate() {
+        for (col_num, tile) in row.iter().enumerate() {
+            let rect = layout.rect(15 + 50 * col_num as i32, 100 + 50 * row_num as i32, 46, 46);
+            tiles.draw(canvas, *tile, rect)?;
+        }
+    }
+    let breakdown = board.score_breakdown();
+    for (i, &(tile, count, points)) in breakdown.iter().take(BREAKDOWN_LINES).enumerate() {
+        let line = format!("{} x{} = {}", tile, count, points);
+        draw_text(
+            canvas,
+            layout,
+            texture_creator,
+            font,
+            &line,
+            230,
+            104 + 24 * i as i32,
+            20,
+        )?;
+    }
+
+    let best_tile = format!(
+        "Best tile: {}{}",
+        board.get_high_card(),
+        new_best(new.high_card)
+    );
+    draw_text(
+        canvas,
+        layout,
+        texture_creator,
+        font,
+        &best_tile,
+        15,
+        312,
+        22,
+    )?;
+    let moves = format!("Moves: {}{}", board.get_moves().len(), new_best(new.moves));
+    draw_text(canvas, layout, texture_creator, font, &moves, 15, 340, 22)?;
+    let ever = format!(
+        "Best ever: {} points, a {} tile",
+        bests.score, bests.high_card
+    );
+    draw_text(canvas, layout, texture_creator, font, &ever, 15, 378, 16)?;
+    let games = format!(
+        "Games played: {}, longest {} moves",
+        bests.games, bests.moves
+    );
+    draw_text(canvas, layout, texture_creator, font, &games, 15, 402, 16)
+}
+
+fn new_best(is_new: bool) -> &'static str {
+    if is_new {
+        " - new best!"
+    } else {
+        ""
+    }
+}
+
+fn draw_menu(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    menu: &Menu<MenuAction>,
+) -> Result<(), ThreesError> {
+    for (i, item) in menu.items().iter().enumerate() {
+        let Area {
+            x,
+            y,
+            width,
+            height,
+        } = item.rect;
+        canvas.set_draw_color(if i == menu.selected() {
+            MENU_SELECTED
+        } else {
+            MENU_BUTTON
+        });
+        canvas
+            .fill_rect(layout.rect(x, y, width, height))
+            .map_err(gui_error("draw a menu"))?;
+        let text_height = height / 2;
+        let text_y = y + (height - text_height) as i32 / 2;
+        let centre = x + width as i32 / 2;
+        draw_text_centred(
+            canvas,
+            layout,
+            texture_creator,
+            font,
+            item.label,
+            centre,
+            text_y,
+            text_height,
+        )?;
+    }
+    Ok(())
+}
+
+/// Washes the whole window out towards the background colour, so a menu
+/// stands out over the game.
+fn fade(canvas: &mut Canvas<Window>, amount: u8) -> Result<(), ThreesError> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(
+        BACKGROUND.r,
+        BACKGROUND.g,
+        BACKGROUND.b,
+        amount,
+    ));
+    let faded = canvas.fill_rect(None).map_err(gui_error("fade the window"));
+    canvas.set_blend_mode(BlendMode::None);
+    faded
+}
+
+/// Writes a line of text `height` layout units tall, centred on `centre_x`.
+#[allow(clippy::too_many_arguments)]
+fn draw_text_centred(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    text: &str,
+    centre_x: i32,
+    y: i32,
+    height: u32,
+) -> Result<(), ThreesError> {
+    let (width, text_height) = font.size_of(text).map_err(gui_error("measure text"))?;
+    let width = width * height / text_height.max(1);
+    let x = centre_x - width as i32 / 2;
+    draw_text(canvas, layout, texture_creator, font, text, x, y, height)
+}
+
+/// Writes a line of text `height` layout units tall with its top left corner
+/// at `(x, y)`.
+#[allow(clippy::too_many_arguments)]
+fn draw_text(
+    canvas: &mut Canvas<Window>,
+    layout: &Layout,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    text: &str,
+    x: i32,
+    y: i32,
+    height: u32,
+) -> Result<(), ThreesError> {
+    let surface = font
+        .render(text)
+        .blended(TEXT_COLOUR)
+        .map_err(gui_error("render text"))?;
+    let width = surface.width() * height / surface.height().max(1);
+    let texture = texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(gui_error("render text"))?;
+    canvas
+        .copy(&texture, None, layout.rect(x, y, width, height))
+        .map_err(gui_error("draw text"))
+}
+
+fn new_board() -> Board {
+    let mut board = Board::new();
+    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+    board
+}
 
-    fn handle_input(&mut self, event_pump: &mut EventPump) -> bool {
-        for event in event_pump.poll_iter() {
+fn key_direction(keycode: Keycode) -> Option<Direction> {
+    match keycode {
+        Keycode::W | Keycode::Up => Some(Direction::Up),
+        Keycode::A | Keycode::Left => Some(Direction::Left),
+        Keycode::S | Keycode::Down => Some(Direction::Down),
+        Keycode::D | Keycode::Right => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+pub fn new_game(
+    board: Board,
+    save_path: Option<PathBuf>,
+    record_path: Option<PathBuf>,
+    options: GuiOptions,
+) -> Result<(), ThreesError> {
+    let mut game = ThreesWindow::new(board, save_path, record_path, options)?;
+    game.play()
+}
+
+/// Plays back a replay. Space plays or pauses, the left and right keys step
+/// backwards and forwards, and q or ESC quits.
+pub fn watch_replay(mut player: ReplayPlayer, options: GuiOptions) -> Result<(), ThreesError> {
+    let (mut canvas, mut windowed) = create_canvas()?;
+    let texture_creator = canvas.texture_creator();
+    let ttf_context = sdl2::ttf::init().map_err(gui_error("start SDL_ttf"))?;
+    let font = options
+        .assets
+        .load_font(&ttf_context, 64)
+        .map_err(gui_error("load the font"))?;
+    let mut tiles = TileRenderer::new(&texture_creator, &font, &options.assets, options.png_tiles)?;
+    let mut event_pump = canvas
+        .window()
+        .subsystem()
+        .sdl()
+        .event_pump()
+        .map_err(gui_error("read events"))?;
+
+    let mut playing = false;
+    let mut next_step = Instant::now();
+    let mut animation: Option<Animation> = None;
+    let mut dirty = true;
+    loop {
+        // Sleep until something happens or the next move is due, unless a
+        // move is still playing out
+        let mut events = vec![];
+        if animation.is_none() && !dirty {
+            if playing {
+                let wait = next_step.saturating_duration_since(Instant::now());
+                events.extend(event_pump.wait_event_timeout(millis_rounded_up(wait)));
+            } else {
+                events.push(event_pump.wait_event());
+            }
+        }
+        events.extend(event_pump.poll_iter());
+
+        for event in events {
+            if handle_window_event(&mut canvas, &mut windowed, &event) {
+                dirty = true;
+                continue;
+            }
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -221,96 +1194,117 @@ impl<'a> ThreesWindow {
                     keycode: Some(Keycode::Q),
                     ..
                 } => {
-                    return false;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::W),
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Up),
-                    ..
-                } => {
-                    self.board.move_up();
-                    return true;
+                    remember_geometry(&canvas, windowed);
+                    return Ok(());
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::A),
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Left),
+                    keycode: Some(Keycode::Space),
                     ..
                 } => {
-                    self.board.move_left();
-                    return true;
+                    playing = !playing;
+                    next_step = Instant::now() + replay_delay(&player);
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::S),
+                    keycode: Some(Keycode::Right),
                     ..
                 }
                 | Event::KeyDown {
-                    keycode: Some(Keycode::Down),
+                    keycode: Some(Keycode::D),
                     ..
                 } => {
-                    self.board.move_down();
-                    return true;
+                    playing = false;
+                    animation = step_replay(&mut player, &options)?;
+                    dirty = true;
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::D),
+                    keycode: Some(Keycode::Left),
                     ..
                 }
                 | Event::KeyDown {
-                    keycode: Some(Keycode::Right),
+                    keycode: Some(Keycode::A),
                     ..
                 } => {
-                    self.board.move_right();
-                    return true;
-                }
-                Event::AppDidEnterBackground { .. } => {
-                    thread::sleep(time::Duration::from_millis(250));
-                }
-                _ => {
-                    return true;
+                    playing = false;
+                    animation = None;
+                    player.step_back();
+                    dirty = true;
                 }
+                Event::Window { .. } => dirty = true,
+                _ => {}
             }
         }
-        true
-    }
 
-    fn handle_end_input(&mut self, event_pump: &mut EventPump) -> PostGameOption {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Q),
-                    ..
-                } => {
-                    return PostGameOption::Quit;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::R),
-                    ..
-                } => {
-                    return PostGameOption::Restart;
-                }
-                Event::AppDidEnterBackground { .. } => {
-                    thread::sleep(time::Duration::from_millis(250));
-                }
-                _ => {
-                    return PostGameOption::DisplayScore;
-                }
-            }
+        if playing && Instant::now() >= next_step {
+            animation = step_replay(&mut player, &options)?;
+            playing = !player.at_end();
+            next_step = Instant::now() + replay_delay(&player);
+            dirty = true;
+        }
+
+        if animation.as_ref().is_some_and(|a| a.is_finished()) {
+            animation = None;
+            dirty = true;
         }
-        PostGameOption::DisplayScore
+        if !dirty && animation.is_none() {
+            continue;
+        }
+        let layout = canvas_layout(&canvas)?;
+        draw_board(
+            &mut canvas,
+            &layout,
+            &mut tiles,
+            player.board(),
+            animation.as_ref(),
+        )?;
+        draw_hud(
+            &mut canvas,
+            &layout,
+            &texture_creator,
+            &font,
+            &mut tiles,
+            player.board(),
+        )?;
+        canvas.present();
+        dirty = false;
     }
 }
 
-pub fn new_game() {
-    let mut game = ThreesWindow::new();
-    game.play();
+/// How long to wait before playing the next move: as long as the player took,
+/// within reason, or half a second for untimed replays.
+fn replay_delay(player: &ReplayPlayer) -> Duration {
+    let delay = player
+        .delay_before_next()
+        .unwrap_or_else(|| Duration::from_millis(500));
+    min(delay, Duration::from_secs(2))
+}
+
+/// Whole milliseconds, rounded up so waiting this long never wakes too early.
+fn millis_rounded_up(duration: Duration) -> u32 {
+    let millis = duration
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(duration.subsec_nanos().div_ceil(1_000_000)));
+    min(millis, u64::from(u32::MAX)) as u32
+}
+
+fn step_replay(
+    player: &mut ReplayPlayer,
+    options: &GuiOptions,
+) -> Result<Option<Animation>, ThreesError> {
+    let before = board_grid(player.board());
+    Ok(player
+        .step_forward()?
+        .and_then(|result| animate(before, result, options)))
+}
+
+#[test]
+fn test_millis_rounded_up() {
+    assert_eq!(millis_rounded_up(Duration::from_millis(0)), 0);
+    assert_eq!(millis_rounded_up(Duration::from_millis(16)), 16);
+    // Rounding a fraction of a millisecond down to 0 would spin the loop
+    assert_eq!(millis_rounded_up(Duration::from_nanos(1)), 1);
+    assert_eq!(millis_rounded_up(Duration::from_micros(999)), 1);
+    assert_eq!(millis_rounded_up(Duration::from_micros(1_001)), 2);
+    assert_eq!(millis_rounded_up(Duration::new(2, 500_000)), 2_001);
+    assert_eq!(millis_rounded_up(Duration::from_secs(u64::MAX)), u32::MAX);
 }
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+
+/// A bounded undo/redo stack of previous states.
+///
+/// A limit of zero disables history entirely. Once the limit is reached the
+/// oldest state is dropped to make room for the newest.
+#[derive(Clone, Debug)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    limit: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(limit: usize) -> History<T> {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.undo.len() > limit {
+            self.undo.pop_front();
+        }
+        // The nearest state to redo is at the end, so keep that end
+        let excess = self.redo.len().saturating_sub(limit);
+        self.redo.drain(..excess);
+    }
+
+    /// Records the state from before a new action. Anything that could have
+    /// been redone is discarded, as the new action has replaced it.
+    pub fn record(&mut self, previous: T) {
+        self.redo.clear();
+        self.push_undo(previous);
+    }
+
+    /// Adds a state to undo to, dropping the oldest if that goes over the
+    /// limit.
+    fn push_undo(&mut self, state: T) {
+        if self.limit == 0 {
+            return;
+        }
+        while self.undo.len() >= self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(state);
+    }
+
+    /// Swaps `current` for the most recently recorded state, if there is one.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// Swaps `current` for the most recently undone state, if there is one.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.push_undo(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut history = History::new(10);
+    history.record(1);
+    history.record(2);
+    assert_eq!(history.undo(3), Some(2));
+    assert_eq!(history.undo(2), Some(1));
+    assert_eq!(history.undo(1), None);
+    assert_eq!(history.redo(1), Some(2));
+    assert_eq!(history.redo(2), Some(3));
+    assert_eq!(history.redo(3), None);
+}
+
+#[test]
+fn test_record_clears_redo() {
+    let mut history = History::new(10);
+    history.record(1);
+    assert_eq!(history.undo(2), Some(1));
+    assert!(history.can_redo());
+    history.record(1);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn test_limit_drops_oldest() {
+    let mut history = History::new(2);
+    history.record(1);
+    history.record(2);
+    history.record(3);
+    assert_eq!(history.undo(4), Some(3));
+    assert_eq!(history.undo(3), Some(2));
+    assert_eq!(history.undo(2), None);
+}
+
+#[test]
+fn test_lowering_limit_keeps_nearest_redo() {
+    let mut history = History::new(10);
+    for state in 1..5 {
+        history.record(state);
+    }
+    assert_eq!(history.undo(5), Some(4));
+    assert_eq!(history.undo(4), Some(3));
+    assert_eq!(history.undo(3), Some(2));
+    history.set_limit(2);
+    assert_eq!(history.redo(2), Some(3));
+    assert_eq!(history.redo(3), Some(4));
+    assert_eq!(history.redo(4), None);
+}
+
+#[test]
+fn test_redo_keeps_to_a_lowered_limit() {
+    let mut history = History::new(3);
+    for state in 1..4 {
+        history.record(state);
+    }
+    assert_eq!(history.undo(4), Some(3));
+    assert_eq!(history.undo(3), Some(2));
+    history.set_limit(1);
+    for _ in 0..3 {
+        assert_eq!(history.redo(2), Some(3));
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo(3), Some(2));
+    }
+    assert_eq!(history.undo(2), None);
+}
+
+#[test]
+fn test_zero_limit_disables_history() {
+    let mut history = History::new(0);
+    history.record(1);
+    assert!(!history.can_undo());
+    assert_eq!(history.undo(2), None);
+}
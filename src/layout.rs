@@ -0,0 +1,111 @@
+use sdl2::rect::Rect;
+
+/// Width of the board, including its margin, in layout units
+pub const BOARD_SIZE: u32 = 420;
+/// Room above the board for the next card, score and move count
+pub const HUD_HEIGHT: u32 = 110;
+/// Size of one board cell, in layout units
+const CELL_SIZE: f32 = 100.0;
+/// Gap around the board, in layout units
+const MARGIN: f32 = 10.0;
+
+/// Where everything goes in a window of a given size.
+///
+/// Everything is laid out in units of a `BOARD_SIZE` by
+/// `BOARD_SIZE + HUD_HEIGHT` design, which is scaled to fit the drawable area
+/// and centred in whatever space is left over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    scale: f32,
+    left: f32,
+    top: f32,
+}
+
+impl Layout {
+    /// The layout for a drawable area of `width` by `height` pixels. On high
+    /// DPI displays this is bigger than the window's size in points.
+    pub fn new(width: u32, height: u32) -> Layout {
+        let design_width = BOARD_SIZE as f32;
+        let design_height = (BOARD_SIZE + HUD_HEIGHT) as f32;
+        let scale = (width as f32 / design_width).min(height as f32 / design_height);
+        Layout {
+            scale,
+            left: (width as f32 - design_width * scale) / 2.0,
+            top: (height as f32 - design_height * scale) / 2.0,
+        }
+    }
+
+    /// Converts a rectangle in layout units to pixels.
+    pub fn rect(&self, x: i32, y: i32, width: u32, height: u32) -> Rect {
+        let left = self.left + x as f32 * self.scale;
+        let top = self.top + y as f32 * self.scale;
+        let right = self.left + (x as f32 + width as f32) * self.scale;
+        let bottom = self.top + (y as f32 + height as f32) * self.scale;
+        Rect::new(
+            left.round() as i32,
+            top.round() as i32,
+            (right.round() - left.round()).max(1.0) as u32,
+            (bottom.round() - top.round()).max(1.0) as u32,
+        )
+    }
+
+    /// Where the tile at a (possibly fractional) board cell is drawn, grown
+    /// or shrunk about its centre by `scale`.
+    pub fn cell(&self, row: f32, col: f32, scale: f32) -> Rect {
+        let size = (CELL_SIZE * scale * self.scale).round().max(1.0) as u32;
+        let centre_x = MARGIN + CELL_SIZE * (col + 0.5);
+        let centre_y = HUD_HEIGHT as f32 + MARGIN + CELL_SIZE * (row + 0.5);
+        Rect::from_center(
+            (
+                (self.left + centre_x * self.scale).round() as i32,
+                (self.top + centre_y * self.scale).round() as i32,
+            ),
+            size,
+            size,
+        )
+    }
+
+    /// The area the board's cells cover.
+    pub fn board(&self) -> Rect {
+        let inner = BOARD_SIZE - 2 * MARGIN as u32;
+        self.rect(
+            MARGIN as i32,
+            (HUD_HEIGHT as f32 + MARGIN) as i32,
+            inner,
+            inner,
+        )
+    }
+}
+
+#[test]
+fn test_design_size_is_drawn_as_is() {
+    let layout = Layout::new(BOARD_SIZE, BOARD_SIZE + HUD_HEIGHT);
+    assert_eq!(layout.rect(0, 0, 100, 50), Rect::new(0, 0, 100, 50));
+    assert_eq!(layout.board(), Rect::new(10, 120, 400, 400));
+    assert_eq!(layout.cell(0.0, 0.0, 1.0), Rect::new(10, 120, 100, 100));
+    assert_eq!(layout.cell(3.0, 3.0, 1.0), Rect::new(310, 420, 100, 100));
+}
+
+#[test]
+fn test_non_square_windows_centre_the_board() {
+    // Too wide: the height decides the scale and the sides are left empty
+    let wide = Layout::new(1060, 530);
+    assert_eq!(wide.board(), Rect::new(330, 120, 400, 400));
+    // Too tall: the width decides and the space is split above and below
+    let tall = Layout::new(420, 1060);
+    assert_eq!(tall.board(), Rect::new(10, 385, 400, 400));
+    // Neither matches the design, so it's shrunk to fit the narrower way
+    let small = Layout::new(210, 1000);
+    assert_eq!(small.board(), Rect::new(5, 428, 200, 200));
+}
+
+#[test]
+fn test_double_pixel_ratio() {
+    // A window 420 by 530 points drawn at two pixels per point
+    let layout = Layout::new(840, 1060);
+    assert_eq!(layout.board(), Rect::new(20, 240, 800, 800));
+    assert_eq!(layout.cell(0.0, 0.0, 1.0), Rect::new(20, 240, 200, 200));
+    assert_eq!(layout.cell(1.5, 0.0, 1.0), Rect::new(20, 540, 200, 200));
+    // Popping tiles grow about their centre
+    assert_eq!(layout.cell(0.0, 0.0, 1.2), Rect::new(0, 220, 240, 240));
+}
@@ -0,0 +1,36 @@
+//! The Threes game engine: the board, the collision and scoring rules and the
+//! decks new cards are drawn from. The terminal and SDL frontends in the
+//! `threes` binary are built on top of this crate.
+
+extern crate rand;
+
+pub mod bests;
+pub mod board;
+pub mod deck;
+pub mod error;
+pub mod history;
+pub mod moves;
+pub mod protocol;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod script;
+pub mod server;
+
+pub use bests::{load_bests, save_bests, NewBests, PersonalBests};
+pub use board::{Board, DEFAULT_HISTORY_LIMIT};
+pub use deck::{generate_basic_stack, generate_bonus_stack};
+pub use error::ThreesError;
+pub use moves::{Direction, Merge, MoveResult, Spawn, TileMove};
+pub use protocol::{
+    error_json, parse_command, run_json, state_json, ProtocolCommand, ProtocolError, Session,
+};
+pub use replay::{load_replay, save_replay, Recorder, Replay, ReplayPlayer};
+pub use rng::GameRng;
+pub use rules::{card_values, handle_collisions, is_valid_card, tile_score, MAX_CARD};
+pub use save::{load_game, save_game, SaveError, SavedGame};
+pub use script::{parse_script_line, summary, ScriptCommand};
+pub use server::{
+    error_line, play_connection, serve, state_line, DEFAULT_IDLE_TIMEOUT, DEFAULT_MAX_CONNECTIONS,
+};
@@ -1,58 +1,77 @@
-extern crate colored;
+extern crate crossterm;
 extern crate getopts;
-extern crate rand;
+#[cfg(feature = "gui")]
 extern crate sdl2;
+extern crate threes;
 
-mod board;
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+mod animation;
+#[cfg(feature = "gui")]
+mod assets;
+#[cfg(feature = "gui")]
+mod controller;
+#[cfg(feature = "gui")]
+mod geometry;
+#[cfg(feature = "gui")]
 mod gui;
+#[cfg(feature = "gui")]
+mod layout;
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+mod menu;
+mod persist;
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+mod swipe;
+mod theme;
+#[cfg(feature = "gui")]
+mod tiles;
+mod tui;
 
+use std::cmp::min;
 use std::env;
-use std::io;
+use std::io::{self, BufRead, IsTerminal};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
 
-use getopts::Options;
+use getopts::{Matches, Options};
 
-use board::Board;
-use gui::new_game;
-
-fn handle_input(input: &str, board: &mut Board) -> bool {
-    let mut moved = false;
-    let mut valid_input = false;
-    match input.to_uppercase().as_ref() {
-        "W" => {
-            moved = board.move_up();
-            valid_input = true
-        }
-        "A" => {
-            moved = board.move_left();
-            valid_input = true
-        }
-        "S" => {
-            moved = board.move_down();
-            valid_input = true
-        }
-        "D" => {
-            moved = board.move_right();
-            valid_input = true
-        }
-        _ => {
-            println!("Enter either W, A, S or D you dummy");
-        }
-    }
-
-    if !moved {
-        println!("Invalid move");
-    }
-
-    valid_input
-}
+#[cfg(feature = "gui")]
+use assets::Assets;
+#[cfg(feature = "gui")]
+use gui::{new_game, watch_replay, GuiOptions, DEFAULT_ANIMATION_MS, DEFAULT_SWIPE_THRESHOLD};
+use persist::{autosave, record_replay};
+use theme::{pick_theme, ColourChoice, Theme};
+use threes::save::default_save_path;
+use threes::{
+    load_game, load_replay, parse_script_line, run_json, serve, summary, Board, Recorder,
+    ReplayPlayer, ScriptCommand, ThreesError, DEFAULT_HISTORY_LIMIT, DEFAULT_IDLE_TIMEOUT,
+    DEFAULT_MAX_CONNECTIONS,
+};
+use tui::print_board;
 
 fn print_help(program: &str, opts: &Options) {
-    let brief = format!("Usage: {} FILE [options]", program);
+    let brief = format!(
+        "Usage: {0} [options]\n       {0} serve --port N [--timeout SECS] [--max-connections N]",
+        program
+    );
     println!("{}", opts.usage(&brief));
     println!(
         "How to play:
 This is a pretty basic simulation of the popular mobile game, Threes.
-Enter W, A, S or D to move tiles up, left, down or right respectively.
+Press the arrow keys or W, A, S or D to move tiles up, left, down or right
+respectively. Press U to undo a move, Y to redo one, or Q to quit.
+
+Playing from a script:
+When input is piped in, each line can hold any number of moves, either run
+together as letters, like wasd, or as words, like up left undo. Anything
+after a # is ignored. Play stops at the end of the input, at q or quit, or
+when the game ends, and a summary is printed: one key and value per line,
+then the grid as four row lines. Scripts start a new game and leave the
+autosave and replays alone unless given --load, --save or --record. For
+example:
+    echo wasdwwdd | threes -t --seed 42 --quiet
 
 Rules:
 - Making a move moves the whole board in that direction if possible
@@ -66,30 +85,195 @@ Have fun!
     );
 }
 
-fn terminal_game() {
-    let mut game_board = Board::new();
-    while game_board.has_moves() {
-        game_board.print();
-        println!("Next card: {}", game_board.get_next_card());
-        let mut valid_input = false;
-        while !valid_input {
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line, something is bad");
-            valid_input = handle_input(input.trim(), &mut game_board);
+/// Where to keep the game as it's played. Scripts only save where they're
+/// told to, so piping moves in never overwrites the player's own game.
+fn save_path(matches: &Matches, script: bool) -> Option<PathBuf> {
+    let path = matches
+        .opt_str("save")
+        .or_else(|| matches.opt_str("load"))
+        .map(PathBuf::from);
+    if script {
+        path
+    } else {
+        path.or_else(default_save_path)
+    }
+}
+
+/// Picks the game to play: a new one dealt from `seed`, an explicitly loaded
+/// one, the last unfinished game from `save_path`, or a brand new one.
+fn starting_board(
+    load_path: Option<PathBuf>,
+    save_path: &Option<PathBuf>,
+    fresh: bool,
+    seed: Option<u64>,
+) -> Result<Board, ThreesError> {
+    let mut board = if let Some(seed) = seed {
+        Board::with_seed(seed)
+    } else if let Some(path) = load_path {
+        load_game(&path).map_err(|e| ThreesError::Save(path, e))?
+    } else {
+        match save_path {
+            Some(ref path) if !fresh && path.exists() => match load_game(path) {
+                Ok(board) if board.has_moves() => board,
+                Ok(_) => Board::new(),
+                Err(e) => {
+                    eprintln!("Ignoring unreadable save {}: {}", path.display(), e);
+                    Board::new()
+                }
+            },
+            _ => Board::new(),
+        }
+    };
+    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+    Ok(board)
+}
+
+/// Plays the moves read from standard input, typed a line at a time or piped
+/// in as a script, until the input runs out, `q` is read or the game ends.
+/// The board is printed after every line unless `quiet`, and a summary of
+/// the game at the end either way.
+fn terminal_game(
+    mut board: Board,
+    save_path: Option<PathBuf>,
+    record_path: Option<PathBuf>,
+    theme: Theme,
+    quiet: bool,
+) -> Result<(), ThreesError> {
+    let mut recorder = Recorder::new(&board);
+    if !quiet {
+        print_board(&board, theme)?;
+    }
+    let stdin = io::stdin();
+    'input: for (index, line) in stdin.lock().lines().enumerate() {
+        for command in parse_script_line(&line?, index + 1)? {
+            if !board.has_moves() {
+                break 'input;
+            }
+            let changed = match command {
+                ScriptCommand::Move(direction) => {
+                    let moved = board.apply(direction)?.moved;
+                    if !moved && !quiet {
+                        eprintln!("Nothing can move {}", direction);
+                    }
+                    moved
+                }
+                ScriptCommand::Undo => {
+                    let undone = board.undo();
+                    if !undone && !quiet {
+                        eprintln!("Nothing to undo");
+                    }
+                    undone
+                }
+                ScriptCommand::Redo => {
+                    let redone = board.redo();
+                    if !redone && !quiet {
+                        eprintln!("Nothing to redo");
+                    }
+                    redone
+                }
+                ScriptCommand::Quit => break 'input,
+            };
+            if changed {
+                recorder.sync(&board);
+                // Save as we go, so Ctrl-C never loses a game
+                autosave(&board, &save_path);
+            }
+        }
+        if !quiet {
+            print_board(&board, theme)?;
+        }
+    }
+    // Scripts only keep a replay when asked, rather than filling up the
+    // replays folder
+    if !board.has_moves() && record_path.is_some() {
+        record_replay(&board, &recorder, &record_path);
+    }
+    print!("{}", summary(&board));
+    Ok(())
+}
+
+fn terminal_replay(mut player: ReplayPlayer, theme: Theme) -> Result<(), ThreesError> {
+    let total = player.replay().moves.len();
+    loop {
+        print_board(player.board(), theme)?;
+        println!(
+            "Move {}/{}. Enter n to step forward, b to step back, p to play to the end or q to quit",
+            player.position(),
+            total
+        );
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            return Ok(());
+        }
+        match input.trim().to_lowercase().as_ref() {
+            "" | "n" => step_replay(&mut player)?,
+            "b" => {
+                if !player.step_back() {
+                    println!("Already at the start");
+                }
+            }
+            "p" => {
+                while !player.at_end() {
+                    let delay = player
+                        .delay_before_next()
+                        .unwrap_or_else(|| Duration::from_millis(500));
+                    thread::sleep(min(delay, Duration::from_secs(2)));
+                    step_replay(&mut player)?;
+                    print_board(player.board(), theme)?;
+                }
+            }
+            "q" => return Ok(()),
+            _ => println!("Enter either n, b, p or q"),
         }
     }
-    game_board.print();
-    println!("Game over!");
-    println!("You scored: {}", game_board.calculate_score());
-    println!();
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let program = args[0].clone();
+fn step_replay(player: &mut ReplayPlayer) -> Result<(), ThreesError> {
+    match player.step_forward()? {
+        Some(result) => println!("Played {}", result.direction),
+        None => println!("End of replay"),
+    }
+    Ok(())
+}
 
+/// Hosts games for bots over TCP on the loopback interface, until killed.
+fn run_server(matches: &Matches) -> Result<(), ThreesError> {
+    let port: u16 = match matches.opt_str("port").map(|port| port.parse()) {
+        Some(Ok(port)) => port,
+        Some(Err(_)) => return Err(ThreesError::Usage("--port needs a port number".to_string())),
+        None => return Err(ThreesError::Usage("serve needs --port N".to_string())),
+    };
+    let timeout = match matches.opt_str("timeout").map(|secs| secs.parse()) {
+        None => DEFAULT_IDLE_TIMEOUT,
+        Some(Ok(secs)) if secs > 0 => Duration::from_secs(secs),
+        Some(_) => {
+            return Err(ThreesError::Usage(
+                "--timeout needs a whole number of seconds, more than 0".to_string(),
+            ))
+        }
+    };
+    let max_connections = match matches.opt_str("max-connections").map(|n| n.parse()) {
+        None => DEFAULT_MAX_CONNECTIONS,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            return Err(ThreesError::Usage(
+                "--max-connections needs a whole number, more than 0".to_string(),
+            ))
+        }
+    };
+    // Only bots on this machine can connect
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| ThreesError::Network(format!("listen on port {}", port), e))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| ThreesError::Network("find the port being listened on".to_string(), e))?;
+    eprintln!("Serving games on {}", address);
+    serve(listener, timeout, max_connections);
+    Ok(())
+}
+
+fn options() -> Options {
     let mut opts = Options::new();
     opts.optflag(
         "t",
@@ -97,20 +281,300 @@ fn main() {
         "run as a playable version in the terminal. It's probably best to play this\
          in a fresh terminal, or one that you don't mind having repeatedly wiped.",
     );
+    opts.optopt(
+        "",
+        "save",
+        "where to keep the game so it can be resumed later. Defaults to an \
+         autosave in your user data directory.",
+        "FILE",
+    );
+    opts.optopt("", "load", "resume the game saved in FILE", "FILE");
+    opts.optflag(
+        "n",
+        "new",
+        "start a new game instead of resuming the last unfinished one",
+    );
+    opts.optopt(
+        "",
+        "seed",
+        "start a new game dealt from the whole number N, so the same moves \
+         always play out the same way",
+        "N",
+    );
+    opts.optflag(
+        "",
+        "quiet",
+        "when playing moves piped in, only print the summary at the end, not \
+         the board after every line",
+    );
+    opts.optopt(
+        "",
+        "protocol",
+        "play for a bot instead of a person, exchanging one line at a time on \
+         standard input and output. Only json is supported.",
+        "FORMAT",
+    );
+    opts.optopt(
+        "",
+        "port",
+        "with serve, the port to take connections on",
+        "N",
+    );
+    opts.optopt(
+        "",
+        "timeout",
+        &format!(
+            "with serve, how many seconds a connection can send nothing before \
+             it's closed. Defaults to {}.",
+            DEFAULT_IDLE_TIMEOUT.as_secs()
+        ),
+        "SECS",
+    );
+    opts.optopt(
+        "",
+        "max-connections",
+        &format!(
+            "with serve, how many games can be played at once. Connections \
+             beyond that are turned away. Defaults to {}.",
+            DEFAULT_MAX_CONNECTIONS
+        ),
+        "N",
+    );
+    opts.optopt(
+        "",
+        "record",
+        "where to write the replay of the game when it ends. Defaults to a new \
+         file in the replays folder of your user data directory.",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "replay",
+        "watch the replay in FILE instead of playing",
+        "FILE",
+    );
+    #[cfg(feature = "gui")]
+    opts.optflag(
+        "",
+        "png-tiles",
+        "draw tiles in the window with the PNG artwork instead",
+    );
+    #[cfg(feature = "gui")]
+    opts.optopt(
+        "",
+        "assets",
+        "draw the window with the font and tile artwork in DIR instead of the \
+         built in ones. DIR is laid out like resources/ in the source.",
+        "DIR",
+    );
+    #[cfg(feature = "gui")]
+    opts.optopt(
+        "",
+        "animation-ms",
+        &format!(
+            "how long each move takes to animate in the window, or 0 to make \
+             moves instant. Defaults to {}.",
+            DEFAULT_ANIMATION_MS
+        ),
+        "MS",
+    );
+    #[cfg(feature = "gui")]
+    opts.optopt(
+        "",
+        "swipe-threshold",
+        &format!(
+            "how far, in pixels, a mouse drag or touch swipe has to go to make \
+             a move. Defaults to {}.",
+            DEFAULT_SWIPE_THRESHOLD
+        ),
+        "PX",
+    );
+    opts.optopt(
+        "",
+        "theme",
+        "how to colour tiles in the terminal: classic, high-contrast, \
+         colour-blind or mono. Defaults to classic.",
+        "NAME",
+    );
+    opts.optopt(
+        "",
+        "color",
+        "whether to colour the terminal: auto, always or never. auto colours \
+         a terminal unless NO_COLOR is set, and is the default.",
+        "WHEN",
+    );
     opts.optflag("h", "help", "print the help menu");
+    opts
+}
 
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(f) => panic!(f.to_string()),
-    };
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("threes: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), ThreesError> {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let opts = options();
+
+    let matches = opts
+        .parse(&args[1..])
+        .map_err(|f| ThreesError::Usage(format!("{}. Try --help.", f)))?;
     if matches.opt_present("h") {
         print_help(&program, &opts);
-        return;
+        return Ok(());
+    }
+
+    match matches.free.first().map(String::as_ref) {
+        Some("serve") if matches.free.len() == 1 => return run_server(&matches),
+        Some(command) => {
+            return Err(ThreesError::Usage(format!(
+                "unknown command '{}'. Try --help.",
+                command
+            )))
+        }
+        None => {}
     }
 
-    if matches.opt_present("t") {
-        terminal_game();
+    let terminal = matches.opt_present("t") || !cfg!(feature = "gui");
+    let theme = match matches.opt_str("theme") {
+        Some(name) => name.parse().map_err(ThreesError::Usage)?,
+        None => Theme::Classic,
+    };
+    let colour = match matches.opt_str("color") {
+        Some(when) => when.parse().map_err(ThreesError::Usage)?,
+        None => ColourChoice::Auto,
+    };
+    let theme = pick_theme(
+        theme,
+        colour,
+        io::stdout().is_terminal(),
+        env::var_os("NO_COLOR").as_deref(),
+    );
+    #[cfg(feature = "gui")]
+    let gui_options = GuiOptions {
+        png_tiles: matches.opt_present("png-tiles"),
+        assets: match matches.opt_str("assets") {
+            None => Assets::embedded(),
+            Some(dir) => Assets::from_dir(Path::new(&dir), matches.opt_present("png-tiles"))
+                .map_err(|e| ThreesError::Gui(format!("couldn't load assets: {}", e)))?,
+        },
+        animation: match matches.opt_str("animation-ms").map(|ms| ms.parse()) {
+            None => Duration::from_millis(DEFAULT_ANIMATION_MS),
+            Some(Ok(ms)) => Duration::from_millis(ms),
+            Some(Err(_)) => {
+                return Err(ThreesError::Usage(
+                    "--animation-ms needs a whole number of milliseconds".to_string(),
+                ))
+            }
+        },
+        swipe_threshold: match matches.opt_str("swipe-threshold").map(|px| px.parse()) {
+            None => DEFAULT_SWIPE_THRESHOLD,
+            Some(Ok(px)) => px,
+            Some(Err(_)) => {
+                return Err(ThreesError::Usage(
+                    "--swipe-threshold needs a whole number of pixels".to_string(),
+                ))
+            }
+        },
+    };
+
+    if let Some(path) = matches.opt_str("replay") {
+        let replay = load_replay(Path::new(&path))
+            .map_err(|e| ThreesError::Save(PathBuf::from(&path), e))?;
+        let player = ReplayPlayer::new(replay);
+        if !terminal {
+            #[cfg(feature = "gui")]
+            return watch_replay(player, gui_options);
+        }
+        return terminal_replay(player, theme);
+    }
+
+    // Keypresses can only be read one at a time from a real terminal
+    let script = terminal && !(io::stdin().is_terminal() && io::stdout().is_terminal());
+    let load_path = matches.opt_str("load").map(PathBuf::from);
+    let save_path = save_path(&matches, script);
+    let seed = match matches.opt_str("seed") {
+        Some(_) if load_path.is_some() => {
+            return Err(ThreesError::Usage(
+                "--seed starts a new game, so it can't be used with --load".to_string(),
+            ))
+        }
+        Some(seed) => Some(
+            seed.parse()
+                .map_err(|_| ThreesError::Usage("--seed needs a whole number".to_string()))?,
+        ),
+        None => None,
+    };
+
+    if let Some(format) = matches.opt_str("protocol") {
+        if format != "json" {
+            return Err(ThreesError::Usage(format!(
+                "unknown protocol '{}', expected json",
+                format
+            )));
+        }
+        // Bots play a fresh game each time, and leave the player's saves alone
+        let board = starting_board(load_path, &None, true, seed)?;
+        let stdin = io::stdin();
+        return Ok(run_json(board, stdin.lock(), &mut io::stdout())?);
+    }
+
+    // Scripts play out the same whatever was last played by hand
+    let fresh = script || matches.opt_present("n");
+    let board = starting_board(load_path, &save_path, fresh, seed)?;
+
+    let record_path = matches.opt_str("record").map(PathBuf::from);
+
+    if !terminal {
+        #[cfg(feature = "gui")]
+        return new_game(board, save_path, record_path, gui_options);
+    }
+    if script {
+        terminal_game(
+            board,
+            save_path,
+            record_path,
+            theme,
+            matches.opt_present("quiet"),
+        )
     } else {
-        new_game();
+        tui::play(board, save_path, record_path, theme)
     }
 }
+
+#[test]
+fn test_scripts_leave_the_autosave_alone() {
+    let parse = |args: &[&str]| options().parse(args).unwrap();
+    assert_eq!(save_path(&parse(&["-t"]), true), None);
+    assert_eq!(save_path(&parse(&["-t"]), false), default_save_path());
+    assert_eq!(
+        save_path(&parse(&["-t", "--save", "script.json"]), true),
+        Some(PathBuf::from("script.json"))
+    );
+    assert_eq!(
+        save_path(&parse(&["-t", "--load", "script.json"]), true),
+        Some(PathBuf::from("script.json"))
+    );
+}
+
+#[test]
+fn test_scripts_start_afresh() {
+    let dir = env::temp_dir().join(format!("threes-script-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("save.json");
+    let mut saved = Board::with_seed(7);
+    let direction = saved.legal_moves()[0];
+    saved.apply(direction).unwrap();
+    threes::save_game(&saved, &path).unwrap();
+
+    let resumed = starting_board(None, &Some(path.clone()), false, None).unwrap();
+    assert_eq!(resumed.get_moves().len(), 1);
+    let script = starting_board(None, &Some(path.clone()), true, None).unwrap();
+    assert!(script.get_moves().is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
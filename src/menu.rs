@@ -0,0 +1,384 @@
+/// What the window is showing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Screen {
+    Title,
+    Playing,
+    Paused,
+    ConfirmQuit,
+    GameOver,
+}
+
+/// What a menu button does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    Play,
+    NewGame,
+    /// Quits, after asking first if there's a game in progress
+    Quit,
+}
+
+/// Where a menu choice, or backing out of a screen, leads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    Show(Screen),
+    /// Deal a new game and start playing it
+    NewGame,
+    Quit,
+}
+
+impl Screen {
+    /// Where choosing `action` on this screen leads. Quitting from the pause
+    /// menu asks first, as a game is in progress.
+    pub fn choose(self, action: MenuAction) -> Transition {
+        match action {
+            MenuAction::Play => Transition::Show(Screen::Playing),
+            MenuAction::NewGame => Transition::NewGame,
+            MenuAction::Quit if self == Screen::Paused => Transition::Show(Screen::ConfirmQuit),
+            MenuAction::Quit => Transition::Quit,
+        }
+    }
+
+    /// Where backing out of this screen leads: the pause and quit menus go
+    /// back to the game, while leaving any other screen quits.
+    pub fn back(self) -> Transition {
+        match self {
+            Screen::Paused | Screen::ConfirmQuit => Transition::Show(Screen::Playing),
+            _ => Transition::Quit,
+        }
+    }
+}
+
+/// A rectangle, in whatever units it was given in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Area {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Area {
+        Area {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the point is inside, counting the top and left edges but not
+    /// the bottom and right ones.
+    pub fn contains(self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && i64::from(x) < i64::from(self.x) + i64::from(self.width)
+            && i64::from(y) < i64::from(self.y) + i64::from(self.height)
+    }
+}
+
+/// A button on a menu.
+#[derive(Clone, Copy, Debug)]
+pub struct MenuItem<T> {
+    pub label: &'static str,
+    pub action: T,
+    /// Where the button goes, in layout units
+    pub rect: Area,
+}
+
+/// Something done to a menu with the keyboard, mouse or a controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuInput {
+    Previous,
+    Next,
+    /// Picks the highlighted item
+    Pick,
+    /// The pointer moved to this drawable pixel
+    Hover(i32, i32),
+    /// The pointer was clicked at this drawable pixel
+    Click(i32, i32),
+}
+
+/// What a menu made of an input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuEvent<T> {
+    /// The player picked an item
+    Chosen(T),
+    /// A different item is highlighted
+    Moved,
+    Ignored,
+}
+
+/// A list of buttons, one of them highlighted, that can be worked with the
+/// keyboard or the mouse.
+#[derive(Clone, Debug)]
+pub struct Menu<T> {
+    items: Vec<MenuItem<T>>,
+    selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(items: Vec<MenuItem<T>>) -> Menu<T> {
+        Menu { items, selected: 0 }
+    }
+
+    pub fn items(&self) -> &[MenuItem<T>] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Highlights the item for `action`, if there is one.
+    pub fn highlight(&mut self, action: T) -> bool
+    where
+        T: PartialEq,
+    {
+        match self.items.iter().position(|item| item.action == action) {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The action of the highlighted item, if there are any items.
+    pub fn chosen(&self) -> Option<T> {
+        self.items.get(self.selected).map(|item| item.action)
+    }
+
+    pub fn previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    /// Moves the highlight or picks an item. The pointer highlights whatever
+    /// it's over and picks it when clicked, with `to_pixels` saying where an
+    /// item's rectangle is drawn.
+    pub fn handle_input<F>(&mut self, input: MenuInput, to_pixels: F) -> MenuEvent<T>
+    where
+        F: Fn(Area) -> Area,
+    {
+        let item_at = |x: i32, y: i32| {
+            self.items
+                .iter()
+                .position(|item| to_pixels(item.rect).contains(x, y))
+        };
+        match input {
+            MenuInput::Previous => {
+                self.previous();
+                MenuEvent::Moved
+            }
+            MenuInput::Next => {
+                self.next();
+                MenuEvent::Moved
+            }
+            MenuInput::Pick => match self.chosen() {
+                Some(action) => MenuEvent::Chosen(action),
+                None => MenuEvent::Ignored,
+            },
+            MenuInput::Hover(x, y) => match item_at(x, y) {
+                Some(index) if index != self.selected => {
+                    self.selected = index;
+                    MenuEvent::Moved
+                }
+                _ => MenuEvent::Ignored,
+            },
+            MenuInput::Click(x, y) => match item_at(x, y) {
+                Some(index) => {
+                    self.selected = index;
+                    MenuEvent::Chosen(self.items[index].action)
+                }
+                None => MenuEvent::Ignored,
+            },
+        }
+    }
+}
+
+/// The buttons for `screen`, which depend on whether the game on the board
+/// has been started. Menus are stacked in the middle of the window, apart
+/// from the game over screen's, which sits along the bottom.
+pub fn menu_for(screen: Screen, game_started: bool) -> Menu<MenuAction> {
+    let labels = match screen {
+        Screen::Title if !game_started => {
+            vec![("Play", MenuAction::Play), ("Quit", MenuAction::Quit)]
+        }
+        Screen::Title => vec![
+            ("Continue", MenuAction::Play),
+            ("New game", MenuAction::NewGame),
+            ("Quit", MenuAction::Quit),
+        ],
+        Screen::Playing => vec![],
+        Screen::Paused => vec![
+            ("Resume", MenuAction::Play),
+            ("New game", MenuAction::NewGame),
+            ("Quit", MenuAction::Quit),
+        ],
+        Screen::ConfirmQuit => vec![
+            ("Keep playing", MenuAction::Play),
+            ("Quit", MenuAction::Quit),
+        ],
+        Screen::GameOver => vec![
+            ("New game", MenuAction::NewGame),
+            ("Quit", MenuAction::Quit),
+        ],
+    };
+    let items = labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, action))| MenuItem {
+            label,
+            action,
+            rect: if screen == Screen::GameOver {
+                Area::new(20 + 200 * i as i32, 455, 180, 50)
+            } else {
+                Area::new(110, 250 + 65 * i as i32, 200, 50)
+            },
+        })
+        .collect();
+    Menu::new(items)
+}
+
+#[test]
+fn test_menu_wraps_around() {
+    let mut menu = menu_for(Screen::Paused, true);
+    assert_eq!(menu.chosen(), Some(MenuAction::Play));
+    menu.previous();
+    assert_eq!(menu.chosen(), Some(MenuAction::Quit));
+    menu.next();
+    assert_eq!(menu.selected(), 0);
+    menu.next();
+    menu.next();
+    menu.next();
+    assert_eq!(menu.selected(), 0);
+
+    let mut empty = menu_for(Screen::Playing, true);
+    empty.next();
+    empty.previous();
+    assert_eq!(empty.chosen(), None);
+}
+
+#[test]
+fn test_area_contains() {
+    let area = Area::new(10, 20, 30, 40);
+    assert!(area.contains(10, 20));
+    assert!(area.contains(39, 59));
+    assert!(!area.contains(40, 59));
+    assert!(!area.contains(39, 60));
+    assert!(!area.contains(9, 30));
+    assert!(Area::new(i32::MAX - 1, 0, 10, 10).contains(i32::MAX, 5));
+}
+
+#[test]
+fn test_menu_inputs() {
+    let layout = |area: Area| area;
+    let mut menu = menu_for(Screen::Title, false);
+    assert_eq!(menu.handle_input(MenuInput::Next, layout), MenuEvent::Moved);
+    assert_eq!(
+        menu.handle_input(MenuInput::Pick, layout),
+        MenuEvent::Chosen(MenuAction::Quit)
+    );
+    // The first button covers (110, 250) to (310, 300)
+    assert_eq!(
+        menu.handle_input(MenuInput::Hover(200, 260), layout),
+        MenuEvent::Moved
+    );
+    assert_eq!(menu.selected(), 0);
+    assert_eq!(
+        menu.handle_input(MenuInput::Hover(210, 270), layout),
+        MenuEvent::Ignored
+    );
+    assert_eq!(
+        menu.handle_input(MenuInput::Click(10, 10), layout),
+        MenuEvent::Ignored
+    );
+    assert_eq!(
+        menu.handle_input(MenuInput::Click(200, 330), layout),
+        MenuEvent::Chosen(MenuAction::Quit)
+    );
+    assert_eq!(menu.selected(), 1);
+}
+
+#[test]
+fn test_title_offers_to_continue_a_started_game() {
+    let labels = |menu: Menu<MenuAction>| -> Vec<&str> {
+        menu.items().iter().map(|item| item.label).collect()
+    };
+    assert_eq!(labels(menu_for(Screen::Title, false)), vec!["Play", "Quit"]);
+    assert_eq!(
+        labels(menu_for(Screen::Title, true)),
+        vec!["Continue", "New game", "Quit"]
+    );
+}
+
+#[test]
+fn test_quitting_a_game_in_progress_asks_first() {
+    assert_eq!(
+        Screen::Paused.choose(MenuAction::Quit),
+        Transition::Show(Screen::ConfirmQuit)
+    );
+    assert_eq!(
+        Screen::ConfirmQuit.choose(MenuAction::Play),
+        Transition::Show(Screen::Playing)
+    );
+    assert_eq!(
+        Screen::ConfirmQuit.choose(MenuAction::Quit),
+        Transition::Quit
+    );
+    assert_eq!(Screen::Title.choose(MenuAction::Quit), Transition::Quit);
+    assert_eq!(Screen::GameOver.choose(MenuAction::Quit), Transition::Quit);
+    assert_eq!(
+        Screen::GameOver.choose(MenuAction::NewGame),
+        Transition::NewGame
+    );
+}
+
+#[test]
+fn test_back() {
+    assert_eq!(Screen::Paused.back(), Transition::Show(Screen::Playing));
+    assert_eq!(
+        Screen::ConfirmQuit.back(),
+        Transition::Show(Screen::Playing)
+    );
+    assert_eq!(Screen::Title.back(), Transition::Quit);
+    assert_eq!(Screen::GameOver.back(), Transition::Quit);
+}
+
+#[test]
+fn test_menu_hit_testing_follows_the_scaling() {
+    // Drawn twice the size, the first button covers (40, 910) to (400, 1010)
+    let doubled = |area: Area| Area::new(area.x * 2, area.y * 2, area.width * 2, area.height * 2);
+    let mut menu = menu_for(Screen::GameOver, true);
+    assert_eq!(
+        menu.handle_input(MenuInput::Click(45, 960), doubled),
+        MenuEvent::Chosen(MenuAction::NewGame)
+    );
+    assert_eq!(
+        menu.handle_input(MenuInput::Click(45, 470), doubled),
+        MenuEvent::Ignored
+    );
+    assert_eq!(
+        menu.handle_input(MenuInput::Hover(500, 1000), doubled),
+        MenuEvent::Moved
+    );
+    assert_eq!(menu.chosen(), Some(MenuAction::Quit));
+}
+
+#[test]
+fn test_highlight() {
+    let mut menu = menu_for(Screen::Paused, true);
+    assert!(menu.highlight(MenuAction::NewGame));
+    assert_eq!(menu.chosen(), Some(MenuAction::NewGame));
+    let mut title = menu_for(Screen::Title, false);
+    assert!(!title.highlight(MenuAction::NewGame));
+    assert_eq!(title.chosen(), Some(MenuAction::Play));
+}
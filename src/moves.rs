@@ -0,0 +1,161 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A direction the whole board can be pushed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    /// The `(row, col)` offset a tile moves by when pushed this way.
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+
+    /// Maps a position along the board onto a `(row, col)` cell.
+    ///
+    /// `line` picks one of the four rows or columns tiles slide along, and
+    /// `step` walks along it starting from the edge tiles are pushed towards,
+    /// so step 0 is where tiles end up and step 3 is where new cards spawn.
+    pub fn cell(self, line: usize, step: usize) -> (usize, usize) {
+        match self {
+            Direction::Up => (step, line),
+            Direction::Down => (3 - step, line),
+            Direction::Left => (line, step),
+            Direction::Right => (line, 3 - step),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s.to_lowercase().as_ref() {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(format!("Unknown direction: {}", s)),
+        }
+    }
+}
+
+/// A tile sliding one cell. Cells are `(row, col)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileMove {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// The value of the tile before it moved
+    pub value: u32,
+}
+
+impl TileMove {
+    /// The `(row, col)` offset the tile travelled by.
+    pub fn vector(&self) -> (i32, i32) {
+        (
+            self.to.0 as i32 - self.from.0 as i32,
+            self.to.1 as i32 - self.from.1 as i32,
+        )
+    }
+}
+
+/// A tile sliding into an occupied cell and combining with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Merge {
+    /// The cell the moving tile came from, which is now empty
+    pub from: (usize, usize),
+    /// The cell both tiles ended up in
+    pub into: (usize, usize),
+    /// The value of the combined tile
+    pub value: u32,
+}
+
+/// The card placed on the board after a successful move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spawn {
+    pub position: (usize, usize),
+    pub value: u32,
+}
+
+/// What happened when a direction was applied to a board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveResult {
+    pub direction: Direction,
+    /// Whether any tile moved. If not, the board is unchanged and no card spawned.
+    pub moved: bool,
+    /// Every tile that slid, including the ones that merged
+    pub movements: Vec<TileMove>,
+    pub merges: Vec<Merge>,
+    pub spawn: Option<Spawn>,
+    /// The card that will be placed by the following move
+    pub next_card: u32,
+    pub score_delta: u64,
+}
+
+#[test]
+fn test_direction_round_trip() {
+    for direction in Direction::ALL.iter() {
+        assert_eq!(Ok(*direction), direction.to_string().parse());
+    }
+    assert_eq!(Ok(Direction::Left), "LEFT".parse());
+    assert!("sideways".parse::<Direction>().is_err());
+}
+
+#[test]
+fn test_tile_move_vector() {
+    let tile_move = TileMove {
+        from: (2, 1),
+        to: (1, 1),
+        value: 3,
+    };
+    assert_eq!(tile_move.vector(), (-1, 0));
+}
+
+#[test]
+fn test_offset_points_towards_pushed_edge() {
+    for direction in Direction::ALL.iter() {
+        let (row, col) = direction.cell(0, 1);
+        let (edge_row, edge_col) = direction.cell(0, 0);
+        assert_eq!(
+            direction.offset(),
+            (edge_row as i32 - row as i32, edge_col as i32 - col as i32)
+        );
+    }
+}
+
+#[test]
+fn test_cells_start_at_pushed_edge() {
+    assert_eq!(Direction::Up.cell(1, 0), (0, 1));
+    assert_eq!(Direction::Down.cell(1, 0), (3, 1));
+    assert_eq!(Direction::Left.cell(2, 0), (2, 0));
+    assert_eq!(Direction::Right.cell(2, 0), (2, 3));
+}
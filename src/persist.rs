@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use threes::replay::default_replay_path;
+use threes::{save_game, save_replay, Board, Recorder};
+
+/// Saves the game in progress, warning rather than failing if it can't.
+pub fn autosave(board: &Board, save_path: &Option<PathBuf>) {
+    if let Some(ref path) = *save_path {
+        if let Err(e) = save_game(board, path) {
+            eprintln!("Couldn't save to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Writes out the replay of a game to `record_path`, or to a new file in the
+/// replays directory if no path was given.
+pub fn record_replay(board: &Board, recorder: &Recorder, record_path: &Option<PathBuf>) {
+    let replay = match recorder.replay(board) {
+        Some(replay) => replay,
+        // Games resumed from old saves don't know their seed
+        None => return,
+    };
+    if let Some(path) = record_path.clone().or_else(default_replay_path) {
+        if let Err(e) = save_replay(&replay, &path) {
+            eprintln!("Couldn't save replay to {}: {}", path.display(), e);
+        }
+    }
+}
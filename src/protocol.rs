@@ -0,0 +1,338 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, BufRead, Write};
+
+use board::{Board, DEFAULT_HISTORY_LIMIT};
+use moves::Direction;
+
+/// Something a bot asked for, one per line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolCommand {
+    /// `move up`, `move down`, `move left` or `move right`
+    Move(Direction),
+    /// `undo`
+    Undo,
+    /// `new-game`, or `new-game SEED` to deal a particular game
+    NewGame(Option<u64>),
+    /// `quit`
+    Quit,
+}
+
+/// Why a command couldn't be carried out. None of these end the session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    UnknownCommand(String),
+    /// The command was known but what came after it wasn't right. The message
+    /// says what was expected.
+    BadArgument(String),
+    /// Nothing on the board can move that way
+    IllegalMove(Direction),
+    NothingToUndo,
+    /// The game has ended, so only `undo`, `new-game` and `quit` make sense
+    GameOver,
+    /// The engine itself went wrong, which is a bug
+    Internal(String),
+}
+
+impl ProtocolError {
+    /// A short name for the kind of error, for bots to match on.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ProtocolError::UnknownCommand(_) => "unknown_command",
+            ProtocolError::BadArgument(_) => "bad_argument",
+            ProtocolError::IllegalMove(_) => "illegal_move",
+            ProtocolError::NothingToUndo => "nothing_to_undo",
+            ProtocolError::GameOver => "game_over",
+            ProtocolError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtocolError::UnknownCommand(ref command) => write!(
+                f,
+                "unknown command '{}', expected move, undo, new-game or quit",
+                command
+            ),
+            ProtocolError::BadArgument(ref reason) => write!(f, "{}", reason),
+            ProtocolError::IllegalMove(direction) => {
+                write!(f, "nothing can move {}", direction)
+            }
+            ProtocolError::NothingToUndo => write!(f, "nothing to undo"),
+            ProtocolError::GameOver => write!(f, "the game is over"),
+            ProtocolError::Internal(ref reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+/// Reads one command. Words are separated by whitespace and can be in any
+/// case.
+pub fn parse_command(line: &str) -> Result<ProtocolCommand, ProtocolError> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or("").to_lowercase();
+    let argument = words.next();
+    if let Some(extra) = words.next() {
+        return Err(ProtocolError::BadArgument(format!(
+            "unexpected '{}' after {}",
+            extra, name
+        )));
+    }
+    let no_argument = |command| match argument {
+        Some(argument) => Err(ProtocolError::BadArgument(format!(
+            "{} doesn't take an argument, but got '{}'",
+            name, argument
+        ))),
+        None => Ok(command),
+    };
+    match name.as_ref() {
+        "move" => match argument.map(str::parse) {
+            Some(Ok(direction)) => Ok(ProtocolCommand::Move(direction)),
+            _ => Err(ProtocolError::BadArgument(
+                "move needs a direction: up, down, left or right".to_string(),
+            )),
+        },
+        "undo" => no_argument(ProtocolCommand::Undo),
+        "quit" => no_argument(ProtocolCommand::Quit),
+        "new-game" => match argument.map(str::parse) {
+            None => Ok(ProtocolCommand::NewGame(None)),
+            Some(Ok(seed)) => Ok(ProtocolCommand::NewGame(Some(seed))),
+            Some(Err(_)) => Err(ProtocolError::BadArgument(
+                "new-game's seed has to be a whole number".to_string(),
+            )),
+        },
+        _ => Err(ProtocolError::UnknownCommand(name)),
+    }
+}
+
+/// One bot's game, and the commands that change it.
+#[derive(Debug)]
+pub struct Session {
+    board: Board,
+}
+
+impl Session {
+    pub fn new(board: Board) -> Session {
+        Session { board }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Carries out `command`. Quitting is left to whoever is reading the
+    /// commands, so does nothing here.
+    pub fn execute(&mut self, command: ProtocolCommand) -> Result<(), ProtocolError> {
+        match command {
+            ProtocolCommand::Move(_) if !self.board.has_moves() => Err(ProtocolError::GameOver),
+            ProtocolCommand::Move(direction) => {
+                let result = self
+                    .board
+                    .apply(direction)
+                    .map_err(|e| ProtocolError::Internal(e.to_string()))?;
+                if result.moved {
+                    Ok(())
+                } else {
+                    Err(ProtocolError::IllegalMove(direction))
+                }
+            }
+            ProtocolCommand::Undo => {
+                if self.board.undo() {
+                    Ok(())
+                } else {
+                    Err(ProtocolError::NothingToUndo)
+                }
+            }
+            ProtocolCommand::NewGame(seed) => {
+                self.board = match seed {
+                    Some(seed) => Board::with_seed(seed),
+                    None => Board::new(),
+                };
+                self.board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+                Ok(())
+            }
+            ProtocolCommand::Quit => Ok(()),
+        }
+    }
+}
+
+/// The board as one line of JSON: the grid, row by row, a hint at the next
+/// card as real Threes gives it ("1", "2", "3", or "+" for anything higher),
+/// the score, how many moves have been played, which directions would move
+/// something and whether the game is over.
+pub fn state_json(board: &Board) -> String {
+    let rows: Vec<String> = board
+        .grid_rows(",")
+        .iter()
+        .map(|row| format!("[{}]", row))
+        .collect();
+    let legal_moves: Vec<String> = board
+        .legal_moves()
+        .iter()
+        .map(|direction| json_string(&direction.to_string()))
+        .collect();
+    format!(
+        "{{\"type\":\"state\",\"grid\":[{}],\"next_card\":{},\"score\":{},\"moves\":{},\
+         \"legal_moves\":[{}],\"game_over\":{}}}",
+        rows.join(","),
+        json_string(&board.next_card_hint()),
+        board.calculate_score(),
+        board.get_moves().len(),
+        legal_moves.join(","),
+        !board.has_moves()
+    )
+}
+
+/// `error` as one line of JSON, with its code and a message for people.
+pub fn error_json(error: &ProtocolError) -> String {
+    format!(
+        "{{\"type\":\"error\",\"error\":{},\"message\":{}}}",
+        json_string(error.code()),
+        json_string(&error.to_string())
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Plays `board` for a bot: writes the state, then after each command read
+/// from `input` writes either the new state or an error, one JSON object per
+/// line. Blank lines are skipped. Stops at `quit` or the end of the input.
+pub fn run_json<R: BufRead, W: Write>(board: Board, input: R, output: &mut W) -> io::Result<()> {
+    let mut session = Session::new(board);
+    writeln!(output, "{}", state_json(session.board()))?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match parse_command(&line) {
+            Ok(ProtocolCommand::Quit) => break,
+            Ok(command) => session
+                .execute(command)
+                .map(|_| state_json(session.board())),
+            Err(e) => Err(e),
+        };
+        writeln!(output, "{}", reply.unwrap_or_else(|e| error_json(&e)))?;
+        // Bots wait for each reply before sending the next command
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(
+        parse_command("move Left"),
+        Ok(ProtocolCommand::Move(Direction::Left))
+    );
+    assert_eq!(parse_command("  undo "), Ok(ProtocolCommand::Undo));
+    assert_eq!(
+        parse_command("new-game"),
+        Ok(ProtocolCommand::NewGame(None))
+    );
+    assert_eq!(
+        parse_command("NEW-GAME 7"),
+        Ok(ProtocolCommand::NewGame(Some(7)))
+    );
+    assert_eq!(parse_command("quit"), Ok(ProtocolCommand::Quit));
+    assert_eq!(parse_command("jump").unwrap_err().code(), "unknown_command");
+    assert_eq!(parse_command("move").unwrap_err().code(), "bad_argument");
+    assert_eq!(
+        parse_command("move up now").unwrap_err().code(),
+        "bad_argument"
+    );
+    assert_eq!(parse_command("undo 2").unwrap_err().code(), "bad_argument");
+}
+
+#[test]
+fn test_state_json() {
+    let board = Board::with_seed(42);
+    let json = state_json(&board);
+    assert!(json.starts_with("{\"type\":\"state\",\"grid\":[["));
+    assert!(json.contains("\"score\":"));
+    assert!(json.contains("\"moves\":0"));
+    assert!(json.contains("\"game_over\":false"));
+    assert!(!json.contains('\n'));
+}
+
+#[test]
+fn test_error_json_escapes_messages() {
+    let error = ProtocolError::UnknownCommand("say\"hi\"\\".to_string());
+    assert_eq!(
+        error_json(&error),
+        "{\"type\":\"error\",\"error\":\"unknown_command\",\"message\":\"unknown command \
+         'say\\\"hi\\\"\\\\', expected move, undo, new-game or quit\"}"
+    );
+}
+
+#[test]
+fn test_run_json_replies_once_per_command() {
+    let input = "undo\n\nfly away\nnew-game 3\nquit\nundo\n";
+    let mut output = vec![];
+    run_json(Board::with_seed(1), input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let replies: Vec<&str> = output.lines().collect();
+    assert_eq!(replies.len(), 4);
+    assert!(replies[0].starts_with("{\"type\":\"state\""));
+    assert!(replies[1].contains("\"error\":\"nothing_to_undo\""));
+    assert!(replies[2].contains("\"error\":\"unknown_command\""));
+    assert_eq!(replies[3], state_json(&Board::with_seed(3)));
+}
+
+#[test]
+fn test_session_rejects_illegal_moves() {
+    let mut session = Session::new(Board::with_seed(5));
+    let mut moved = false;
+    for &direction in Direction::ALL.iter() {
+        let legal = session.board().legal_moves().contains(&direction);
+        let result = session.execute(ProtocolCommand::Move(direction));
+        if legal {
+            assert_eq!(result, Ok(()));
+            moved = true;
+        } else {
+            assert_eq!(result, Err(ProtocolError::IllegalMove(direction)));
+        }
+    }
+    assert!(moved);
+}
+
+#[test]
+fn test_empty_last_cell_is_not_game_over() {
+    let mut game = Board::with_seed(1).to_saved_game();
+    game.state = [[3, 2, 6, 2], [1, 6, 12, 6], [6, 24, 2, 2], [3, 1, 3, 0]];
+    game.high_card = 24;
+    let mut session = Session::new(Board::from_saved_game(game));
+    let json = state_json(session.board());
+    assert!(
+        json.contains("\"legal_moves\":[\"down\",\"right\"],\"game_over\":false"),
+        "{}",
+        json
+    );
+    assert_eq!(
+        session.execute(ProtocolCommand::Move(Direction::Down)),
+        Ok(())
+    );
+}
@@ -0,0 +1,393 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use board::Board;
+use error::ThreesError;
+use moves::{Direction, MoveResult};
+use save::{data_dir, SaveError};
+
+/// Bumped whenever a rule change means a seed and a list of moves would no
+/// longer play out the same way, so old replays aren't silently misplayed.
+pub const RULESET_VERSION: u32 = 1;
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+const REPLAY_HEADER: &str = "threes-replay";
+
+/// A single recorded move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayMove {
+    pub direction: Direction,
+    /// Milliseconds since the start of the game, if the move was timed
+    pub at_ms: Option<u64>,
+}
+
+/// Everything needed to watch a game again: where it started and every move
+/// that was played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub ruleset: u32,
+    pub moves: Vec<ReplayMove>,
+}
+
+impl Replay {
+    /// The replay of the game `board` has played so far, or `None` if the
+    /// game's seed isn't known. `times` optionally timestamps each move.
+    pub fn from_board(board: &Board, times: &[Option<u64>]) -> Option<Replay> {
+        let moves = board
+            .get_moves()
+            .iter()
+            .enumerate()
+            .map(|(i, direction)| ReplayMove {
+                direction: *direction,
+                at_ms: times.get(i).cloned().unwrap_or(None),
+            })
+            .collect();
+        Some(Replay {
+            seed: board.get_seed()?,
+            ruleset: RULESET_VERSION,
+            moves,
+        })
+    }
+
+    pub fn to_replay_string(&self) -> String {
+        let mut out = format!("{} {}\n", REPLAY_HEADER, REPLAY_FORMAT_VERSION);
+        out.push_str(&format!("seed {}\n", self.seed));
+        out.push_str(&format!("ruleset {}\n", self.ruleset));
+        for m in self.moves.iter() {
+            match m.at_ms {
+                Some(at_ms) => out.push_str(&format!("move {} {}\n", m.direction, at_ms)),
+                None => out.push_str(&format!("move {}\n", m.direction)),
+            }
+        }
+        out
+    }
+
+    pub fn from_replay_str(contents: &str) -> Result<Replay, SaveError> {
+        let mut lines = contents.lines().enumerate();
+        match lines
+            .next()
+            .map(|(_, l)| l.split_whitespace().collect::<Vec<_>>())
+        {
+            Some(ref words) if words.len() == 2 && words[0] == REPLAY_HEADER => {
+                if words[1] != REPLAY_FORMAT_VERSION.to_string() {
+                    return Err(malformed(1, "unsupported replay version"));
+                }
+            }
+            _ => return Err(malformed(1, "not a threes replay")),
+        }
+
+        let mut seed = None;
+        let mut ruleset = None;
+        let mut moves = vec![];
+        for (i, line) in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["seed", value] => {
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| malformed(i + 1, "invalid seed"))?,
+                    )
+                }
+                ["ruleset", value] => {
+                    ruleset = Some(
+                        value
+                            .parse()
+                            .map_err(|_| malformed(i + 1, "invalid ruleset"))?,
+                    )
+                }
+                ["move", direction] | ["move", direction, _] => moves.push(ReplayMove {
+                    direction: direction
+                        .parse()
+                        .map_err(|e: String| malformed(i + 1, &e))?,
+                    at_ms: match words.get(2) {
+                        Some(at_ms) => Some(
+                            at_ms
+                                .parse()
+                                .map_err(|_| malformed(i + 1, "invalid timestamp"))?,
+                        ),
+                        None => None,
+                    },
+                }),
+                _ => return Err(malformed(i + 1, "unrecognised line")),
+            }
+        }
+
+        let ruleset = ruleset.ok_or_else(|| malformed(0, "missing ruleset"))?;
+        if ruleset != RULESET_VERSION {
+            return Err(SaveError::Malformed(format!(
+                "replay uses ruleset {}, but this version of the game plays ruleset {}",
+                ruleset, RULESET_VERSION
+            )));
+        }
+        Ok(Replay {
+            seed: seed.ok_or_else(|| malformed(0, "missing seed"))?,
+            ruleset,
+            moves,
+        })
+    }
+}
+
+fn malformed(line: usize, reason: &str) -> SaveError {
+    if line == 0 {
+        SaveError::Malformed(reason.to_string())
+    } else {
+        SaveError::Malformed(format!("line {}: {}", line, reason))
+    }
+}
+
+pub fn save_replay(replay: &Replay, path: &Path) -> Result<(), SaveError> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    fs::write(path, replay.to_replay_string())?;
+    Ok(())
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, SaveError> {
+    Replay::from_replay_str(&fs::read_to_string(path)?)
+}
+
+/// A fresh file in the replays directory for a game finishing now.
+pub fn default_replay_path() -> Option<PathBuf> {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut path = data_dir()?;
+    path.push("replays");
+    path.push(format!(
+        "game-{}-{:03}.replay",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    ));
+    Some(path)
+}
+
+/// Timestamps moves as they're played, so a replay can be saved with timings.
+#[derive(Debug)]
+pub struct Recorder {
+    started: Instant,
+    times: Vec<Option<u64>>,
+}
+
+impl Recorder {
+    /// Starts timing moves made from now on. Moves already played on `board`,
+    /// say in a resumed game, are left untimed.
+    pub fn new(board: &Board) -> Recorder {
+        Recorder {
+            started: Instant::now(),
+            times: vec![None; board.get_moves().len()],
+        }
+    }
+
+    /// Catches up with the moves played on `board`. Call this after every
+    /// move, undo or redo: undone moves lose their times, new ones are
+    /// stamped with the current time.
+    pub fn sync(&mut self, board: &Board) {
+        let played = board.get_moves().len();
+        self.times.truncate(played);
+        let now = self.started.elapsed();
+        let now = now.as_secs() * 1000 + u64::from(now.subsec_millis());
+        while self.times.len() < played {
+            self.times.push(Some(now));
+        }
+    }
+
+    pub fn replay(&self, board: &Board) -> Option<Replay> {
+        Replay::from_board(board, &self.times)
+    }
+}
+
+/// The replay's moves don't play out on its seed: it was recorded with
+/// different rules, or has been edited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Desync {
+    pub move_number: usize,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} of the replay can't be played", self.move_number)
+    }
+}
+
+/// Steps through a replay forwards and backwards.
+#[derive(Debug)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    board: Board,
+    position: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let mut board = Board::with_seed(replay.seed);
+        board.set_history_limit(replay.moves.len());
+        ReplayPlayer {
+            replay,
+            board,
+            position: 0,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// How many moves have been played back so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.position == self.replay.moves.len()
+    }
+
+    /// Plays the next move. Returns `Ok(None)` at the end of the replay.
+    pub fn step_forward(&mut self) -> Result<Option<MoveResult>, ThreesError> {
+        let next = match self.replay.moves.get(self.position) {
+            Some(next) => *next,
+            None => return Ok(None),
+        };
+        let result = self.board.apply(next.direction)?;
+        if !result.moved {
+            return Err(ThreesError::Desync(Desync {
+                move_number: self.position + 1,
+            }));
+        }
+        self.position += 1;
+        Ok(Some(result))
+    }
+
+    /// Takes back the last move played. Returns false at the start.
+    pub fn step_back(&mut self) -> bool {
+        if self.position == 0 || !self.board.undo() {
+            return false;
+        }
+        self.position -= 1;
+        true
+    }
+
+    /// How long the player waited before making the next move, if the replay
+    /// has timings for it.
+    pub fn delay_before_next(&self) -> Option<Duration> {
+        let next = self.replay.moves.get(self.position)?.at_ms?;
+        let previous = if self.position == 0 {
+            0
+        } else {
+            self.replay.moves[self.position - 1].at_ms?
+        };
+        Some(Duration::from_millis(next.saturating_sub(previous)))
+    }
+}
+
+#[cfg(test)]
+fn play_some_moves(seed: u64) -> Board {
+    let mut board = Board::with_seed(seed);
+    for direction in Direction::ALL.iter().cycle().take(20) {
+        board.apply(*direction).unwrap();
+    }
+    board
+}
+
+#[test]
+fn test_replay_string_round_trip() {
+    let board = play_some_moves(4);
+    let replay = Replay::from_board(&board, &[Some(0), Some(250), None, Some(900)]).unwrap();
+    assert_eq!(replay.moves[1].at_ms, Some(250));
+    assert_eq!(replay.moves[2].at_ms, None);
+    assert_eq!(replay.moves[4].at_ms, None);
+    let loaded = Replay::from_replay_str(&replay.to_replay_string()).unwrap();
+    assert_eq!(replay, loaded);
+}
+
+#[test]
+fn test_player_reproduces_game() {
+    let board = play_some_moves(12);
+    let mut player = ReplayPlayer::new(Replay::from_board(&board, &[]).unwrap());
+    while player.step_forward().unwrap().is_some() {}
+    assert!(player.at_end());
+    assert_eq!(player.board().get_board(), board.get_board());
+    assert_eq!(player.board().get_next_card(), board.get_next_card());
+}
+
+#[test]
+fn test_player_steps_back() {
+    let board = play_some_moves(12);
+    let mut player = ReplayPlayer::new(Replay::from_board(&board, &[]).unwrap());
+    let start = player.board().get_board().to_vec();
+    player.step_forward().unwrap();
+    let after_one = player.board().get_board().to_vec();
+    player.step_forward().unwrap();
+    assert!(player.step_back());
+    assert_eq!(player.board().get_board(), &after_one[..]);
+    assert!(player.step_back());
+    assert_eq!(player.board().get_board(), &start[..]);
+    assert!(!player.step_back());
+    assert_eq!(player.position(), 0);
+}
+
+#[test]
+fn test_player_reports_desync() {
+    let replay = Replay {
+        seed: 1,
+        ruleset: RULESET_VERSION,
+        moves: vec![
+            ReplayMove {
+                direction: Direction::Up,
+                at_ms: None,
+            };
+            10
+        ],
+    };
+    let mut player = ReplayPlayer::new(replay);
+    let mut result = Ok(Some(()));
+    while let Ok(Some(_)) = result {
+        result = player.step_forward().map(|r| r.map(|_| ()));
+    }
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_delay_before_next() {
+    let board = play_some_moves(2);
+    let player = ReplayPlayer::new(Replay::from_board(&board, &[Some(100), Some(400)]).unwrap());
+    assert_eq!(player.delay_before_next(), Some(Duration::from_millis(100)));
+}
+
+#[test]
+fn test_recorder_follows_undo() {
+    let mut board = Board::with_seed(6);
+    board.set_history_limit(10);
+    board.apply(Direction::Left).unwrap();
+    let mut recorder = Recorder::new(&board);
+    for direction in Direction::ALL.iter() {
+        board.apply(*direction).unwrap();
+        recorder.sync(&board);
+    }
+    board.undo();
+    recorder.sync(&board);
+    let replay = recorder.replay(&board).unwrap();
+    assert_eq!(replay.moves.len(), board.get_moves().len());
+    assert_eq!(replay.moves[0].at_ms, None);
+    assert!(replay.moves[1..].iter().all(|m| m.at_ms.is_some()));
+}
+
+#[test]
+fn test_rejects_other_rulesets() {
+    let text = "threes-replay 1\nseed 3\nruleset 99\nmove up\n";
+    assert!(Replay::from_replay_str(text).is_err());
+    let text = "threes-replay 1\nseed 3\nruleset 1\nmove sideways\n";
+    assert!(Replay::from_replay_str(text).is_err());
+}
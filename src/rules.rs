@@ -0,0 +1,146 @@
+fn calculate_coefficient(x: u32) -> u32 {
+    // There's probably a much more elegant way of doing this
+    let mut y = x;
+    let mut coefficient = 1;
+    while y > 3 {
+        y /= 2;
+        coefficient += 1;
+    }
+    coefficient
+}
+
+/// The biggest card a tile can hold: 3 doubled 30 times. Two of these can't
+/// be combined any further. Their sum never fitted in a `u32`, so the cap
+/// doesn't change how any game that could be played before plays out, and
+/// didn't need a new `RULESET_VERSION`.
+pub const MAX_CARD: u32 = 3 << 30;
+
+/// Every card value the game can produce, smallest first: 1, 2, 3, 6, 12...
+pub fn card_values() -> Vec<u32> {
+    let mut values = vec![1, 2];
+    let mut next = 3;
+    while next <= MAX_CARD {
+        values.push(next);
+        next = match next.checked_mul(2) {
+            Some(doubled) => doubled,
+            None => break,
+        };
+    }
+    values
+}
+
+/// How many points a single tile is worth. Empty spaces, 1s and 2s are not
+/// worth points.
+pub fn tile_score(tile: u32) -> u64 {
+    if tile <= 2 {
+        return 0;
+    }
+    3u64.pow(calculate_coefficient(tile))
+}
+
+/// Whether `tile` is a card that can exist in a game: a 1, a 2 or a 3 doubled
+/// any number of times.
+pub fn is_valid_card(tile: u32) -> bool {
+    if tile == 1 || tile == 2 {
+        return true;
+    }
+    tile.is_multiple_of(3) && (tile / 3).is_power_of_two()
+}
+
+/// Resolves `y` sliding into `x`, returning the resulting tile or `None` if
+/// the two tiles can't combine. An empty space (`0`) accepts anything.
+pub fn handle_collisions(x: u32, y: u32) -> Option<u32> {
+    if x == 0 {
+        Some(y)
+    } else if (x == 1 && y == 2) | (y == 1 && x == 2) {
+        Some(3)
+    } else if x == y && x > 2 && x < MAX_CARD {
+        Some(x * 2)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_collision_x_zero() {
+    let (x, y) = (0, 3);
+    let result = handle_collisions(x, y);
+    assert_eq!(result, Some(y));
+}
+
+#[test]
+fn test_collision_one_two() {
+    let (x, y) = (1, 2);
+    assert_eq!(handle_collisions(x, y), Some(3));
+    assert_eq!(handle_collisions(y, x), Some(3));
+}
+
+#[test]
+fn test_collision_of_like_values() {
+    assert_eq!(handle_collisions(3, 3), Some(6));
+    assert_eq!(handle_collisions(6, 6), Some(12));
+    assert_eq!(handle_collisions(12, 12), Some(24));
+    assert_eq!(handle_collisions(24, 24), Some(48));
+    assert_eq!(handle_collisions(48, 48), Some(96));
+    assert_eq!(handle_collisions(96, 96), Some(192));
+    assert_eq!(handle_collisions(192, 192), Some(384));
+}
+
+#[test]
+fn test_collision_fails() {
+    assert_eq!(handle_collisions(1, 1), None);
+    assert_eq!(handle_collisions(2, 2), None);
+    assert_eq!(handle_collisions(3, 1), None);
+    assert_eq!(handle_collisions(3, 2), None);
+    assert_eq!(handle_collisions(1, 3), None);
+    assert_eq!(handle_collisions(2, 3), None);
+}
+
+#[test]
+fn test_tile_score() {
+    assert_eq!(tile_score(0), 0);
+    assert_eq!(tile_score(1), 0);
+    assert_eq!(tile_score(2), 0);
+    assert_eq!(tile_score(3), 3);
+    assert_eq!(tile_score(6), 9);
+    assert_eq!(tile_score(48), 243);
+    assert_eq!(tile_score(768), 19683);
+}
+
+#[test]
+fn test_is_valid_card() {
+    for card in [1, 2, 3, 6, 12, 24, 48, 96, 192, 384, 768, 1536, 3072, 6144].iter() {
+        assert!(is_valid_card(*card), "{} should be valid", card);
+    }
+    for card in [0, 4, 5, 9, 18, 762, 1524, 3048].iter() {
+        assert!(!is_valid_card(*card), "{} should be invalid", card);
+    }
+}
+
+#[test]
+fn test_card_values() {
+    let values = card_values();
+    assert_eq!(&values[..6], &[1, 2, 3, 6, 12, 24]);
+    assert!(values.contains(&768));
+    assert!(values.contains(&1536));
+    assert!(values.contains(&3072));
+    assert!(values.contains(&6144));
+    assert_eq!(*values.last().unwrap(), MAX_CARD);
+    assert!(values.iter().all(|v| is_valid_card(*v)));
+}
+
+#[test]
+fn test_max_card_only_stops_merges_that_overflowed() {
+    for value in card_values().into_iter().filter(|&value| value > 2) {
+        assert_eq!(handle_collisions(value, value), value.checked_mul(2));
+    }
+}
+
+#[test]
+fn test_max_card_doesnt_combine() {
+    assert_eq!(handle_collisions(MAX_CARD, MAX_CARD), None);
+    assert_eq!(
+        handle_collisions(MAX_CARD / 2, MAX_CARD / 2),
+        Some(MAX_CARD)
+    );
+}
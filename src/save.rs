@@ -0,0 +1,325 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use board::Board;
+use moves::Direction;
+use rules::is_valid_card;
+
+/// Written at the top of every save file so old files can still be read if
+/// the format changes.
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+const SAVE_HEADER: &str = "threes-save";
+
+/// Everything needed to carry on a game exactly where it was left off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavedGame {
+    pub state: [[u32; 4]; 4],
+    pub high_card: u32,
+    pub next_card: u32,
+    pub basic_cards: Vec<u32>,
+    pub bonus_cards: Vec<u32>,
+    /// Where the random number generator had got to
+    pub rng_state: u64,
+    /// The seed the game started from. Unknown for games saved by version 1.
+    pub seed: Option<u64>,
+    /// The moves played since the game started
+    pub moves: Vec<Direction>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    Malformed(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "{}", e),
+            SaveError::UnsupportedVersion(v) => write!(
+                f,
+                "save file is version {}, but only versions up to {} are supported",
+                v, SAVE_FORMAT_VERSION
+            ),
+            SaveError::Malformed(ref reason) => write!(f, "save file is corrupt: {}", reason),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+impl SavedGame {
+    /// Renders the game in the save file format: a version header followed by
+    /// one `key values...` line per field.
+    pub fn to_save_string(&self) -> String {
+        let grid: Vec<u32> = self
+            .state
+            .iter()
+            .flat_map(|row| row.iter().cloned())
+            .collect();
+        let mut out = format!("{} {}\n", SAVE_HEADER, SAVE_FORMAT_VERSION);
+        out.push_str(&field("grid", &grid));
+        out.push_str(&field("high_card", &[self.high_card]));
+        out.push_str(&field("next_card", &[self.next_card]));
+        out.push_str(&field("basic_cards", &self.basic_cards));
+        out.push_str(&field("bonus_cards", &self.bonus_cards));
+        out.push_str(&format!("rng {}\n", self.rng_state));
+        if let Some(seed) = self.seed {
+            out.push_str(&format!("seed {}\n", seed));
+        }
+        out.push_str("moves");
+        for direction in self.moves.iter() {
+            out.push_str(&format!(" {}", direction));
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn from_save_str(contents: &str) -> Result<SavedGame, SaveError> {
+        let mut lines = contents.lines();
+        let header = lines.next().unwrap_or("");
+        let version = match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [SAVE_HEADER, version] => version
+                .parse::<u32>()
+                .map_err(|_| malformed("invalid version"))?,
+            _ => return Err(malformed("not a threes save file")),
+        };
+        if version == 0 || version > SAVE_FORMAT_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        let mut grid = None;
+        let mut high_card = None;
+        let mut next_card = None;
+        let mut basic_cards = None;
+        let mut bonus_cards = None;
+        let mut rng_state = None;
+        let mut seed = None;
+        let mut moves = vec![];
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap_or("");
+            let values: Vec<&str> = words.collect();
+            match key {
+                "grid" => grid = Some(parse_cards(key, &values)?),
+                "high_card" => high_card = Some(parse_single(key, &values)?),
+                "next_card" => next_card = Some(parse_single(key, &values)?),
+                "basic_cards" => basic_cards = Some(parse_cards(key, &values)?),
+                "bonus_cards" => bonus_cards = Some(parse_cards(key, &values)?),
+                "rng" => rng_state = Some(parse_u64(key, &values)?),
+                "seed" => seed = Some(parse_u64(key, &values)?),
+                "moves" => {
+                    moves = values
+                        .iter()
+                        .map(|v| v.parse().map_err(|e: String| malformed(&e)))
+                        .collect::<Result<Vec<Direction>, SaveError>>()?
+                }
+                _ => return Err(malformed(&format!("unknown field '{}'", key))),
+            }
+        }
+
+        let grid = grid.ok_or_else(|| malformed("missing grid"))?;
+        if grid.len() != 16 || grid.iter().any(|&tile| tile != 0 && !is_valid_card(tile)) {
+            return Err(malformed("grid must hold 16 valid tiles"));
+        }
+        let mut state = [[0; 4]; 4];
+        for (i, tile) in grid.into_iter().enumerate() {
+            state[i / 4][i % 4] = tile;
+        }
+        let game = SavedGame {
+            state,
+            high_card: high_card.ok_or_else(|| malformed("missing high_card"))?,
+            next_card: next_card.ok_or_else(|| malformed("missing next_card"))?,
+            basic_cards: basic_cards.ok_or_else(|| malformed("missing basic_cards"))?,
+            bonus_cards: bonus_cards.ok_or_else(|| malformed("missing bonus_cards"))?,
+            rng_state: rng_state.ok_or_else(|| malformed("missing rng"))?,
+            seed,
+            moves,
+        };
+        let mut cards = game.basic_cards.iter().chain(game.bonus_cards.iter());
+        if !is_valid_card(game.next_card) || cards.any(|&card| !is_valid_card(card)) {
+            return Err(malformed("invalid card in deck"));
+        }
+        Ok(game)
+    }
+}
+
+fn field(key: &str, values: &[u32]) -> String {
+    let mut line = key.to_string();
+    for value in values {
+        line.push_str(&format!(" {}", value));
+    }
+    line.push('\n');
+    line
+}
+
+fn malformed(reason: &str) -> SaveError {
+    SaveError::Malformed(reason.to_string())
+}
+
+fn parse_cards(key: &str, values: &[&str]) -> Result<Vec<u32>, SaveError> {
+    values
+        .iter()
+        .map(|v| {
+            v.parse()
+                .map_err(|_| malformed(&format!("invalid value '{}' in {}", v, key)))
+        })
+        .collect()
+}
+
+fn parse_u64(key: &str, values: &[&str]) -> Result<u64, SaveError> {
+    match values {
+        [value] => value
+            .parse()
+            .map_err(|_| malformed(&format!("invalid {}", key))),
+        _ => Err(malformed(&format!("{} needs exactly one value", key))),
+    }
+}
+
+fn parse_single(key: &str, values: &[&str]) -> Result<u32, SaveError> {
+    match parse_cards(key, values)?.as_slice() {
+        [value] => Ok(*value),
+        _ => Err(malformed(&format!("{} needs exactly one value", key))),
+    }
+}
+
+pub fn save_game(board: &Board, path: &Path) -> Result<(), SaveError> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    // Write to the side and swap it in, so a crash mid-save can't lose the game
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, board.to_saved_game().to_save_string())?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn load_game(path: &Path) -> Result<Board, SaveError> {
+    let contents = fs::read_to_string(path)?;
+    Ok(Board::from_saved_game(SavedGame::from_save_str(&contents)?))
+}
+
+/// Where the game keeps its files between runs: `$XDG_DATA_HOME/threes`,
+/// `~/.local/share/threes` or `%APPDATA%\threes`.
+pub fn data_dir() -> Option<PathBuf> {
+    let dir = if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(dir)
+    } else if let Some(dir) = std::env::var_os("APPDATA") {
+        PathBuf::from(dir)
+    } else {
+        let mut dir = PathBuf::from(std::env::var_os("HOME")?);
+        dir.push(".local");
+        dir.push("share");
+        dir
+    };
+    Some(dir.join("threes"))
+}
+
+/// Where unfinished games are kept between runs.
+pub fn default_save_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("autosave.txt"))
+}
+
+#[test]
+fn test_save_string_round_trip() {
+    let mut board = Board::with_seed(3);
+    board.apply(Direction::Left).unwrap();
+    let saved = board.to_saved_game();
+    let loaded = SavedGame::from_save_str(&saved.to_save_string()).unwrap();
+    assert_eq!(saved, loaded);
+}
+
+#[test]
+fn test_loaded_game_plays_on_identically() {
+    let mut board = Board::with_seed(11);
+    board.apply(Direction::Up).unwrap();
+    let saved = board.to_saved_game().to_save_string();
+    let mut loaded = Board::from_saved_game(SavedGame::from_save_str(&saved).unwrap());
+    for direction in Direction::ALL.iter().cycle().take(30) {
+        assert_eq!(
+            board.apply(*direction).unwrap(),
+            loaded.apply(*direction).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_save_file_round_trip() {
+    let path = std::env::temp_dir().join(format!("threes-save-test-{}", std::process::id()));
+    let board = Board::with_seed(8);
+    save_game(&board, &path).unwrap();
+    let loaded = load_game(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(board.to_saved_game(), loaded.to_saved_game());
+}
+
+#[test]
+fn test_reads_version_one() {
+    let v1 = "threes-save 1
+grid 3 1 2 0 3 2 3 2 3 1 0 0 0 0 1 0
+high_card 3
+next_card 3
+basic_cards 1 2
+bonus_cards
+rng 13064758930783842026
+";
+    let game = SavedGame::from_save_str(v1).unwrap();
+    assert_eq!(game.state[0], [3, 1, 2, 0]);
+    assert_eq!(game.basic_cards, vec![1, 2]);
+    assert_eq!(game.seed, None);
+    assert!(game.moves.is_empty());
+}
+
+#[test]
+fn test_keeps_seed_and_moves() {
+    let mut board = Board::with_seed(17);
+    for direction in Direction::ALL.iter() {
+        board.apply(*direction).unwrap();
+    }
+    let saved = board.to_saved_game().to_save_string();
+    let loaded = SavedGame::from_save_str(&saved).unwrap();
+    assert_eq!(loaded.seed, Some(17));
+    assert_eq!(&loaded.moves[..], board.get_moves());
+}
+
+#[test]
+fn test_rejects_other_versions() {
+    match SavedGame::from_save_str("threes-save 99\n") {
+        Err(SaveError::UnsupportedVersion(99)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_rejects_bad_files() {
+    let good = Board::with_seed(1).to_saved_game().to_save_string();
+    let bad = [
+        String::new(),
+        "not a save".to_string(),
+        good.replace("grid ", "grid 5 "),
+        good.replace("rng", "seed"),
+        good.lines()
+            .filter(|l| !l.starts_with("next_card"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    ];
+    for contents in bad.iter() {
+        match SavedGame::from_save_str(contents) {
+            Err(SaveError::Malformed(_)) => {}
+            other => panic!("unexpected {:?} for {:?}", other, contents),
+        }
+    }
+}
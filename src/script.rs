@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+use board::Board;
+use error::ThreesError;
+use moves::Direction;
+
+/// One step of a game played from a script or a pipe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptCommand {
+    Move(Direction),
+    Undo,
+    Redo,
+    /// Stops reading, leaving the rest of the input unplayed
+    Quit,
+}
+
+/// Reads one line of a move script. Moves can be run together as letters,
+/// like `wasd`, or written out as words, like `up left`: w, a, s and d move,
+/// u undoes, y redoes and q quits. Whitespace and commas separate words, and
+/// everything after a `#` is a comment.
+///
+/// `line_number` counts from 1 and is only used in error messages.
+pub fn parse_script_line(
+    line: &str,
+    line_number: usize,
+) -> Result<Vec<ScriptCommand>, ThreesError> {
+    let line = line.split('#').next().unwrap_or("");
+    let mut commands = vec![];
+    let mut column = 1;
+    for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+        if let Some(command) = word_command(token) {
+            commands.push(command);
+        } else {
+            for (offset, c) in token.chars().enumerate() {
+                match letter_command(c) {
+                    Some(command) => commands.push(command),
+                    None => {
+                        return Err(ThreesError::Input(format!(
+                            "line {}, column {}: '{}' isn't a move. Use w, a, s or d to move, \
+                             u to undo, y to redo and q to quit, or the words up, down, left, \
+                             right, undo, redo and quit",
+                            line_number,
+                            column + offset,
+                            c
+                        )))
+                    }
+                }
+            }
+        }
+        column += token.chars().count() + 1;
+    }
+    Ok(commands)
+}
+
+fn word_command(word: &str) -> Option<ScriptCommand> {
+    if let Ok(direction) = word.parse() {
+        return Some(ScriptCommand::Move(direction));
+    }
+    match word.to_lowercase().as_ref() {
+        "undo" => Some(ScriptCommand::Undo),
+        "redo" => Some(ScriptCommand::Redo),
+        "quit" => Some(ScriptCommand::Quit),
+        _ => None,
+    }
+}
+
+fn letter_command(letter: char) -> Option<ScriptCommand> {
+    match letter.to_ascii_lowercase() {
+        'w' => Some(ScriptCommand::Move(Direction::Up)),
+        'a' => Some(ScriptCommand::Move(Direction::Left)),
+        's' => Some(ScriptCommand::Move(Direction::Down)),
+        'd' => Some(ScriptCommand::Move(Direction::Right)),
+        'u' => Some(ScriptCommand::Undo),
+        'y' => Some(ScriptCommand::Redo),
+        'q' => Some(ScriptCommand::Quit),
+        _ => None,
+    }
+}
+
+/// Describes where a game has got to in a form that's easy for other
+/// programs to read: one `key value` pair per line, with the grid as four
+/// `row` lines at the end.
+pub fn summary(board: &Board) -> String {
+    let mut summary = String::new();
+    if let Some(seed) = board.get_seed() {
+        let _ = writeln!(summary, "seed {}", seed);
+    }
+    let _ = writeln!(summary, "moves {}", board.get_moves().len());
+    let _ = writeln!(summary, "score {}", board.calculate_score());
+    let _ = writeln!(summary, "high_card {}", board.get_high_card());
+    let _ = writeln!(summary, "next_card {}", board.get_next_card());
+    let game_over = if board.has_moves() { "no" } else { "yes" };
+    let _ = writeln!(summary, "game_over {}", game_over);
+    for row in board.grid_rows(" ") {
+        let _ = writeln!(summary, "row {}", row);
+    }
+    summary
+}
+
+#[test]
+fn test_parse_letters_and_words() {
+    use self::ScriptCommand::*;
+
+    let commands = parse_script_line("wAsd, up Left,undo u y  # then quit", 1).unwrap();
+    assert_eq!(
+        commands,
+        vec![
+            Move(Direction::Up),
+            Move(Direction::Left),
+            Move(Direction::Down),
+            Move(Direction::Right),
+            Move(Direction::Up),
+            Move(Direction::Left),
+            Undo,
+            Undo,
+            Redo,
+        ]
+    );
+    assert_eq!(parse_script_line("", 1).unwrap(), vec![]);
+    assert_eq!(parse_script_line("q", 1).unwrap(), vec![Quit]);
+}
+
+#[test]
+fn test_parse_rejects_unknown_input() {
+    match parse_script_line("wa sdx", 3) {
+        Err(ThreesError::Input(message)) => {
+            assert!(message.starts_with("line 3, column 6: 'x'"), "{}", message)
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_summary() {
+    let board = Board::with_seed(42);
+    let summary = summary(&board);
+    let lines: Vec<&str> = summary.lines().collect();
+    assert_eq!(lines[0], "seed 42");
+    assert_eq!(lines[1], "moves 0");
+    assert!(lines.contains(&"game_over no"));
+    assert_eq!(lines.iter().filter(|l| l.starts_with("row ")).count(), 4);
+}
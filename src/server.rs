@@ -0,0 +1,274 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use board::{Board, DEFAULT_HISTORY_LIMIT};
+use protocol::{parse_command, ProtocolCommand, ProtocolError, Session};
+
+/// How long a connection can go without sending a command before it's closed,
+/// unless the server is told otherwise.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How many games can be played at once, unless the server is told otherwise.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// How long to wait before accepting again after failing to, so running out
+/// of file handles doesn't spin a core until some connections close.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Hosts games for bots connecting over TCP, each connection playing its own
+/// game on its own thread, with up to `max_connections` playing at once.
+/// Never returns: failing to accept a connection is logged and the server
+/// carries on.
+///
+/// The protocol is one line each way. On connecting a client is sent the
+/// state of a new game, and after that every command gets one reply:
+///
+/// - `new-game` or `new-game SEED`, `move DIRECTION`, `undo` and `state` reply
+///   with the state, or `error CODE MESSAGE` if they can't be carried out
+/// - `quit` replies `bye` and closes the connection
+///
+/// A connection that sends nothing for `idle_timeout` is sent `bye timeout`
+/// and closed, and one made while the server is full is sent `bye busy` and
+/// closed straight away.
+pub fn serve(listener: TcpListener, idle_timeout: Duration, max_connections: usize) {
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            // One connection failing is no reason to stop serving the rest
+            Err(e) => {
+                eprintln!("Couldn't accept a connection: {}", e);
+                if e.kind() != ErrorKind::ConnectionAborted {
+                    thread::sleep(ACCEPT_RETRY_DELAY);
+                }
+                continue;
+            }
+        };
+        if open.fetch_add(1, Ordering::SeqCst) >= max_connections {
+            open.fetch_sub(1, Ordering::SeqCst);
+            let _ = writeln!(stream, "bye busy");
+            continue;
+        }
+        let open = Arc::clone(&open);
+        thread::spawn(move || {
+            // A client that disconnects without quitting only ends its own game
+            let _ = play_connection(stream, idle_timeout);
+            open.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Plays one connection's game until it quits, disconnects or times out. A
+/// client that stops reading its replies times out too.
+pub fn play_connection(stream: TcpStream, idle_timeout: Duration) -> io::Result<()> {
+    stream.set_read_timeout(Some(idle_timeout))?;
+    stream.set_write_timeout(Some(idle_timeout))?;
+    let mut output = stream.try_clone()?;
+    let mut board = Board::new();
+    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+    let mut session = Session::new(board);
+    writeln!(output, "{}", state_line(session.board()))?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                writeln!(output, "bye timeout")?;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let reply = if line.eq_ignore_ascii_case("state") {
+            Ok(())
+        } else {
+            match parse_command(line) {
+                Ok(ProtocolCommand::Quit) => {
+                    writeln!(output, "bye")?;
+                    return Ok(());
+                }
+                Ok(command) => session.execute(command),
+                Err(e) => Err(e),
+            }
+        };
+        match reply {
+            Ok(()) => writeln!(output, "{}", state_line(session.board()))?,
+            Err(e) => writeln!(output, "{}", error_line(&e))?,
+        }
+    }
+    Ok(())
+}
+
+/// The board as `key=value` pairs on one line, after the word `state`:
+///
+/// ```text
+/// state seed=42 moves=0 score=9 next_card=3 legal=up,down,left,right game_over=no grid=0,1,0,0/0,0,0,3/3,3,1,1/1,0,2,2
+/// ```
+///
+/// `next_card` is the hint real Threes gives, so `+` for a bonus card,
+/// `legal` is `none` once nothing can move, and the grid is written row by
+/// row. `seed` is left out for games that weren't dealt from one.
+pub fn state_line(board: &Board) -> String {
+    let mut line = "state".to_string();
+    if let Some(seed) = board.get_seed() {
+        line.push_str(&format!(" seed={}", seed));
+    }
+    let legal: Vec<String> = board
+        .legal_moves()
+        .iter()
+        .map(|direction| direction.to_string())
+        .collect();
+    line.push_str(&format!(
+        " moves={} score={} next_card={} legal={} game_over={} grid={}",
+        board.get_moves().len(),
+        board.calculate_score(),
+        board.next_card_hint(),
+        if legal.is_empty() {
+            "none".to_string()
+        } else {
+            legal.join(",")
+        },
+        if legal.is_empty() { "yes" } else { "no" },
+        board.grid_rows(",").join("/")
+    ));
+    line
+}
+
+/// `error` as `error CODE MESSAGE`, using the same codes as the JSON protocol.
+pub fn error_line(error: &ProtocolError) -> String {
+    format!("error {} {}", error.code(), error)
+}
+
+#[cfg(test)]
+fn start_test_server(idle_timeout: Duration) -> ::std::net::SocketAddr {
+    start_limited_test_server(idle_timeout, DEFAULT_MAX_CONNECTIONS)
+}
+
+#[cfg(test)]
+fn start_limited_test_server(
+    idle_timeout: Duration,
+    max_connections: usize,
+) -> ::std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, idle_timeout, max_connections));
+    address
+}
+
+#[cfg(test)]
+fn connect(address: ::std::net::SocketAddr) -> (TcpStream, BufReader<TcpStream>) {
+    let stream = TcpStream::connect(address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let reader = BufReader::new(stream.try_clone().unwrap());
+    (stream, reader)
+}
+
+#[cfg(test)]
+fn send(stream: &mut TcpStream, reader: &mut BufReader<TcpStream>, command: &str) -> String {
+    writeln!(stream, "{}", command).unwrap();
+    read_reply(reader)
+}
+
+#[cfg(test)]
+fn read_reply(reader: &mut BufReader<TcpStream>) -> String {
+    let mut reply = String::new();
+    reader.read_line(&mut reply).unwrap();
+    reply.trim_end().to_string()
+}
+
+#[test]
+fn test_seeded_sessions_play_the_same() {
+    let address = start_test_server(DEFAULT_IDLE_TIMEOUT);
+    let (mut first, mut first_reader) = connect(address);
+    let (mut second, mut second_reader) = connect(address);
+    assert!(read_reply(&mut first_reader).starts_with("state "));
+    assert!(read_reply(&mut second_reader).starts_with("state "));
+
+    let dealt = send(&mut first, &mut first_reader, "new-game 42");
+    assert_eq!(dealt, state_line(&Board::with_seed(42)));
+    assert_eq!(send(&mut second, &mut second_reader, "new-game 42"), dealt);
+
+    let direction = Board::with_seed(42).legal_moves()[0];
+    let command = format!("move {}", direction);
+    let moved = send(&mut first, &mut first_reader, &command);
+    assert!(moved.contains(" moves=1 "), "{}", moved);
+    assert_eq!(send(&mut second, &mut second_reader, &command), moved);
+    assert_eq!(send(&mut first, &mut first_reader, "state"), moved);
+
+    // Each connection has its own game
+    let undone = send(&mut second, &mut second_reader, "undo");
+    assert_eq!(undone, dealt);
+    assert_eq!(send(&mut first, &mut first_reader, "state"), moved);
+}
+
+#[test]
+fn test_errors_and_quit() {
+    let address = start_test_server(DEFAULT_IDLE_TIMEOUT);
+    let (mut stream, mut reader) = connect(address);
+    read_reply(&mut reader);
+    assert_eq!(
+        send(&mut stream, &mut reader, "undo"),
+        "error nothing_to_undo nothing to undo"
+    );
+    assert!(send(&mut stream, &mut reader, "jump").starts_with("error unknown_command "));
+    assert!(send(&mut stream, &mut reader, "new-game x").starts_with("error bad_argument "));
+    assert_eq!(send(&mut stream, &mut reader, "quit"), "bye");
+    assert_eq!(read_reply(&mut reader), "");
+}
+
+#[test]
+fn test_idle_sessions_time_out() {
+    let address = start_test_server(Duration::from_millis(100));
+    let (_stream, mut reader) = connect(address);
+    read_reply(&mut reader);
+    assert_eq!(read_reply(&mut reader), "bye timeout");
+    assert_eq!(read_reply(&mut reader), "");
+}
+
+#[test]
+fn test_full_server_turns_connections_away() {
+    let address = start_limited_test_server(DEFAULT_IDLE_TIMEOUT, 1);
+    let (mut first, mut first_reader) = connect(address);
+    assert!(read_reply(&mut first_reader).starts_with("state "));
+
+    let (_second, mut second_reader) = connect(address);
+    assert_eq!(read_reply(&mut second_reader), "bye busy");
+    assert_eq!(read_reply(&mut second_reader), "");
+
+    // Once the first game ends there's room again
+    assert_eq!(send(&mut first, &mut first_reader, "quit"), "bye");
+    assert_eq!(read_reply(&mut first_reader), "");
+    let mut reply = "bye busy".to_string();
+    for _ in 0..50 {
+        let (_third, mut third_reader) = connect(address);
+        reply = read_reply(&mut third_reader);
+        if reply != "bye busy" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(reply.starts_with("state "), "{}", reply);
+}
+
+#[test]
+fn test_empty_last_cell_is_not_game_over() {
+    let mut game = Board::with_seed(1).to_saved_game();
+    game.state = [[3, 2, 6, 2], [1, 6, 12, 6], [6, 24, 2, 2], [3, 1, 3, 0]];
+    game.high_card = 24;
+    let board = Board::from_saved_game(game);
+    let line = state_line(&board);
+    assert!(line.contains(" legal=down,right game_over=no "), "{}", line);
+    // The move a client would send next is played, not refused
+    let mut session = Session::new(board);
+    let command = parse_command("move down").unwrap();
+    assert_eq!(session.execute(command), Ok(()));
+}
@@ -0,0 +1,54 @@
+use threes::Direction;
+
+/// The direction a drag from `start` to `end` points in, once it's gone at
+/// least `threshold` pixels. Diagonal drags snap to whichever way they went
+/// furthest.
+pub fn drag_direction(start: (i32, i32), end: (i32, i32), threshold: u32) -> Option<Direction> {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    if (dx.abs().max(dy.abs()) as u32) < threshold {
+        None
+    } else if dx.abs() > dy.abs() {
+        Some(if dx > 0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if dy > 0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
+
+#[test]
+fn test_drags_snap_to_the_longer_axis() {
+    let start = (100, 100);
+    assert_eq!(
+        drag_direction(start, (150, 120), 30),
+        Some(Direction::Right)
+    );
+    assert_eq!(drag_direction(start, (50, 80), 30), Some(Direction::Left));
+    assert_eq!(drag_direction(start, (110, 160), 30), Some(Direction::Down));
+    assert_eq!(drag_direction(start, (130, 40), 30), Some(Direction::Up));
+}
+
+#[test]
+fn test_drags_count_from_the_threshold() {
+    let start = (0, 0);
+    assert_eq!(drag_direction(start, (30, 0), 30), Some(Direction::Right));
+    assert_eq!(drag_direction(start, (29, 0), 30), None);
+    assert_eq!(drag_direction(start, (0, -30), 30), Some(Direction::Up));
+    assert_eq!(drag_direction(start, (29, -29), 30), None);
+}
+
+#[test]
+fn test_dragging_back_cancels() {
+    let start = (200, 200);
+    assert_eq!(
+        drag_direction(start, (260, 200), 30),
+        Some(Direction::Right)
+    );
+    assert_eq!(drag_direction(start, (210, 205), 30), None);
+}
@@ -0,0 +1,269 @@
+use crossterm::queue;
+use crossterm::style::{Attribute, Color, Colored, Print, SetAttribute};
+use crossterm::style::{SetBackgroundColor, SetForegroundColor};
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// How tiles are coloured in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// The blues, reds and whites of the real game
+    Classic,
+    /// Only the 16 standard terminal colours, at their boldest
+    HighContrast,
+    /// Blue and vermillion from the Okabe-Ito palette, which stay apart for
+    /// every common kind of colour blindness
+    ColourBlind,
+    /// No colour at all, just bold and reversed text
+    Monochrome,
+    /// No styling whatsoever, for output that isn't going to a terminal
+    Plain,
+}
+
+/// Whether to colour terminal output, as given to `--color`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColourChoice {
+    /// Colour when writing to a terminal, unless `NO_COLOR` is set
+    Auto,
+    Always,
+    Never,
+}
+
+/// How to draw one tile, or any other piece of text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::Classic,
+        Theme::HighContrast,
+        Theme::ColourBlind,
+        Theme::Monochrome,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::HighContrast => "high-contrast",
+            Theme::ColourBlind => "colour-blind",
+            Theme::Monochrome => "mono",
+            Theme::Plain => "plain",
+        }
+    }
+
+    /// How to draw a tile of `value`, given the highest tile made so far. 1s
+    /// and 2s each have their own colour, 3 and up are bold, and the high
+    /// card stands out from the rest.
+    pub fn cell(self, value: u32, high_card: u32) -> CellStyle {
+        let rgb = |r, g, b| Some(Color::Rgb { r, g, b });
+        let (foreground, background) = match (self, value) {
+            (Theme::Plain, _) => return CellStyle::default(),
+            (Theme::Monochrome, _) => (None, None),
+            (Theme::Classic, 0) => (None, rgb(187, 217, 217)),
+            (Theme::Classic, 1) => (rgb(255, 255, 255), rgb(102, 204, 255)),
+            (Theme::Classic, 2) => (rgb(255, 255, 255), rgb(255, 102, 128)),
+            (Theme::Classic, v) if v == high_card => (rgb(255, 82, 102), rgb(255, 236, 170)),
+            (Theme::Classic, _) => (rgb(0, 0, 0), rgb(255, 255, 255)),
+            (Theme::HighContrast, 0) => (None, None),
+            (Theme::HighContrast, 1) => (Some(Color::White), Some(Color::DarkBlue)),
+            (Theme::HighContrast, 2) => (Some(Color::White), Some(Color::DarkRed)),
+            (Theme::HighContrast, v) if v == high_card => (Some(Color::Black), Some(Color::Yellow)),
+            (Theme::HighContrast, _) => (Some(Color::Black), Some(Color::White)),
+            (Theme::ColourBlind, 0) => (None, rgb(60, 60, 60)),
+            (Theme::ColourBlind, 1) => (rgb(255, 255, 255), rgb(0, 114, 178)),
+            (Theme::ColourBlind, 2) => (rgb(0, 0, 0), rgb(230, 159, 0)),
+            (Theme::ColourBlind, v) if v == high_card => (rgb(0, 0, 0), rgb(240, 228, 66)),
+            (Theme::ColourBlind, _) => (rgb(0, 0, 0), rgb(255, 255, 255)),
+        };
+        CellStyle {
+            foreground,
+            background,
+            bold: value >= 3,
+            // Without colour, reversing the high card is all that marks it out
+            reverse: self == Theme::Monochrome && value >= 3 && value == high_card,
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Theme, String> {
+        match name {
+            "classic" => Ok(Theme::Classic),
+            "high-contrast" => Ok(Theme::HighContrast),
+            "colour-blind" | "color-blind" => Ok(Theme::ColourBlind),
+            "mono" | "monochrome" => Ok(Theme::Monochrome),
+            _ => {
+                let names: Vec<&str> = Theme::ALL.iter().map(|theme| theme.name()).collect();
+                Err(format!(
+                    "unknown theme '{}', expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ColourChoice {
+    type Err = String;
+
+    fn from_str(choice: &str) -> Result<ColourChoice, String> {
+        match choice {
+            "auto" => Ok(ColourChoice::Auto),
+            "always" => Ok(ColourChoice::Always),
+            "never" => Ok(ColourChoice::Never),
+            _ => Err(format!(
+                "--color should be auto, always or never, not '{}'",
+                choice
+            )),
+        }
+    }
+}
+
+/// The theme to draw with, given what `--color` said, whether output is
+/// going to a terminal and the value of `NO_COLOR`, if it's set. Following
+/// https://no-color.org, a non-empty `NO_COLOR` leaves only bold and reversed
+/// text unless colour was asked for outright, while output that isn't going
+/// to a terminal gets no styling.
+pub fn pick_theme(
+    theme: Theme,
+    choice: ColourChoice,
+    to_terminal: bool,
+    no_color: Option<&OsStr>,
+) -> Theme {
+    // The choice is made here, so crossterm mustn't check NO_COLOR as well
+    Colored::set_ansi_color_disabled(false);
+    let no_color = no_color.is_some_and(|value| !value.is_empty());
+    match choice {
+        ColourChoice::Always => theme,
+        ColourChoice::Never => Theme::Plain,
+        ColourChoice::Auto if !to_terminal => Theme::Plain,
+        ColourChoice::Auto if no_color => Theme::Monochrome,
+        ColourChoice::Auto => theme,
+    }
+}
+
+/// Writes `text` in `style`, then puts the style back to normal.
+pub fn queue_styled<W: Write>(out: &mut W, text: &str, style: CellStyle) -> io::Result<()> {
+    if style == CellStyle::default() {
+        return queue!(out, Print(text));
+    }
+    if let Some(colour) = style.foreground {
+        queue!(out, SetForegroundColor(colour))?;
+    }
+    if let Some(colour) = style.background {
+        queue!(out, SetBackgroundColor(colour))?;
+    }
+    if style.bold {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    if style.reverse {
+        queue!(out, SetAttribute(Attribute::Reverse))?;
+    }
+    // Resetting the attributes resets the colours too
+    queue!(out, Print(text), SetAttribute(Attribute::Reset))
+}
+
+#[test]
+fn test_pick_theme() {
+    use self::ColourChoice::*;
+
+    let set = Some(OsStr::new("1"));
+    let empty = Some(OsStr::new(""));
+    let cases = [
+        // (choice, to_terminal, NO_COLOR, expected)
+        (Auto, true, None, Theme::Classic),
+        (Auto, true, empty, Theme::Classic),
+        (Auto, true, set, Theme::Monochrome),
+        (Auto, false, None, Theme::Plain),
+        (Auto, false, set, Theme::Plain),
+        (Always, true, None, Theme::Classic),
+        (Always, true, set, Theme::Classic),
+        (Always, false, None, Theme::Classic),
+        (Always, false, set, Theme::Classic),
+        (Never, true, None, Theme::Plain),
+        (Never, true, set, Theme::Plain),
+        (Never, false, None, Theme::Plain),
+        (Never, false, set, Theme::Plain),
+    ];
+    for &(choice, to_terminal, no_color, expected) in cases.iter() {
+        assert_eq!(
+            pick_theme(Theme::Classic, choice, to_terminal, no_color),
+            expected,
+            "{:?}, to_terminal {}, NO_COLOR {:?}",
+            choice,
+            to_terminal,
+            no_color
+        );
+    }
+}
+
+#[test]
+fn test_cell_bold_from_three() {
+    for &theme in Theme::ALL.iter() {
+        assert!(!theme.cell(0, 48).bold);
+        assert!(!theme.cell(1, 48).bold);
+        assert!(!theme.cell(2, 48).bold);
+        assert!(theme.cell(3, 48).bold);
+        assert!(theme.cell(48, 48).bold);
+    }
+    assert_eq!(Theme::Plain.cell(48, 48), CellStyle::default());
+}
+
+#[test]
+fn test_cell_highlights_the_high_card() {
+    for &theme in [Theme::Classic, Theme::HighContrast, Theme::ColourBlind].iter() {
+        assert_ne!(theme.cell(48, 48), theme.cell(24, 48), "{}", theme);
+        assert_eq!(theme.cell(12, 48), theme.cell(24, 48), "{}", theme);
+    }
+    assert!(Theme::Monochrome.cell(48, 48).reverse);
+    assert!(!Theme::Monochrome.cell(24, 48).reverse);
+    // Not even when a 1 or 2 is somehow the highest card
+    assert!(!Theme::Monochrome.cell(2, 2).reverse);
+}
+
+#[test]
+fn test_parse_theme() {
+    assert_eq!("classic".parse(), Ok(Theme::Classic));
+    assert_eq!("high-contrast".parse(), Ok(Theme::HighContrast));
+    assert_eq!("colour-blind".parse(), Ok(Theme::ColourBlind));
+    assert_eq!("color-blind".parse(), Ok(Theme::ColourBlind));
+    assert_eq!("mono".parse(), Ok(Theme::Monochrome));
+    assert_eq!("monochrome".parse(), Ok(Theme::Monochrome));
+    for &theme in Theme::ALL.iter() {
+        assert_eq!(theme.to_string().parse(), Ok(theme));
+    }
+    assert_eq!(
+        "plain".parse::<Theme>(),
+        Err(
+            "unknown theme 'plain', expected one of classic, high-contrast, colour-blind, mono"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_parse_colour_choice() {
+    assert_eq!("auto".parse(), Ok(ColourChoice::Auto));
+    assert_eq!("always".parse(), Ok(ColourChoice::Always));
+    assert_eq!("never".parse(), Ok(ColourChoice::Never));
+    assert_eq!(
+        "sometimes".parse::<ColourChoice>(),
+        Err("--color should be auto, always or never, not 'sometimes'".to_string())
+    );
+}
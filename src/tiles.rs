@@ -0,0 +1,190 @@
+use sdl2::image::ImageRWops;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::rwops::RWops;
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+use std::collections::HashMap;
+use std::iter;
+
+use assets::Assets;
+use gui::gui_error;
+use threes::{card_values, ThreesError};
+
+/// Cards from here up get the gold high card style.
+const HIGH_CARD: u32 = 384;
+
+pub const BACKGROUND: Color = Color {
+    r: 250,
+    g: 250,
+    b: 250,
+    a: 255,
+};
+
+/// Draws tiles in the classic Threes style at any size: blue 1s, red 2s and
+/// white cards from 3 up, each with a darker edge along the bottom. PNG
+/// artwork can be used instead for the values that have it.
+pub struct TileRenderer<'a> {
+    texture_creator: &'a TextureCreator<WindowContext>,
+    font: &'a Font<'a, 'a>,
+    images: HashMap<u32, Texture<'a>>,
+    labels: HashMap<String, Texture<'a>>,
+}
+
+impl<'a> TileRenderer<'a> {
+    pub fn new(
+        texture_creator: &'a TextureCreator<WindowContext>,
+        font: &'a Font<'a, 'a>,
+        assets: &Assets,
+        png_tiles: bool,
+    ) -> Result<TileRenderer<'a>, ThreesError> {
+        let mut images = HashMap::new();
+        if png_tiles {
+            for value in iter::once(0).chain(card_values()) {
+                if let Some(bytes) = assets.tile_image(value) {
+                    let couldnt_load =
+                        |e| ThreesError::Gui(format!("couldn't load the {} tile: {}", value, e));
+                    let rwops = RWops::from_bytes(bytes).map_err(couldnt_load)?;
+                    let surface = rwops.load_png().map_err(couldnt_load)?;
+                    let texture = texture_creator
+                        .create_texture_from_surface(&surface)
+                        .map_err(|e| couldnt_load(e.to_string()))?;
+                    images.insert(value, texture);
+                }
+            }
+        }
+        Ok(TileRenderer {
+            texture_creator,
+            font,
+            images,
+            labels: HashMap::new(),
+        })
+    }
+
+    /// Draws `value` filling the cell `rect`. Zero draws an empty space.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        value: u32,
+        rect: Rect,
+    ) -> Result<(), ThreesError> {
+        if let Some(image) = self.images.get(&value) {
+            return canvas
+                .copy(image, None, rect)
+                .map_err(gui_error("draw a tile"));
+        }
+
+        let card = leave_gap(rect);
+        if value == 0 {
+            canvas.set_draw_color(Color::RGB(187, 217, 217));
+            return canvas.fill_rect(card).map_err(gui_error("draw a tile"));
+        }
+        self.draw_card(canvas, card, value, &value.to_string())
+    }
+
+    /// Draws the "+" card that hints the next card is a bonus card.
+    pub fn draw_bonus_hint(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        rect: Rect,
+    ) -> Result<(), ThreesError> {
+        self.draw_card(canvas, leave_gap(rect), 3, "+")
+    }
+
+    /// Draws a card styled like `value` with `text` written on it.
+    fn draw_card(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        card: Rect,
+        value: u32,
+        text: &str,
+    ) -> Result<(), ThreesError> {
+        // The darker bottom edge makes the card look like it's standing up
+        let (face_colour, edge_colour) = card_colours(value);
+        let edge_height = card.height() / 8;
+        let face = Rect::new(
+            card.x(),
+            card.y(),
+            card.width(),
+            card.height() - edge_height,
+        );
+        canvas.set_draw_color(edge_colour);
+        canvas.fill_rect(card).map_err(gui_error("draw a tile"))?;
+        canvas.set_draw_color(face_colour);
+        canvas.fill_rect(face).map_err(gui_error("draw a tile"))?;
+        if value >= HIGH_CARD {
+            let border = (card.width() / 25).max(1);
+            canvas.set_draw_color(edge_colour);
+            for i in 0..border {
+                let inset = Rect::new(
+                    face.x() + i as i32,
+                    face.y() + i as i32,
+                    face.width() - 2 * i,
+                    face.height() - 2 * i,
+                );
+                canvas.draw_rect(inset).map_err(gui_error("draw a tile"))?;
+            }
+        }
+
+        if !self.labels.contains_key(text) {
+            let surface = self
+                .font
+                .render(text)
+                .blended(label_colour(value))
+                .map_err(gui_error("render a tile label"))?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(gui_error("render a tile label"))?;
+            self.labels.insert(text.to_string(), texture);
+        }
+        let label = &self.labels[text];
+        let query = label.query();
+        canvas
+            .copy(label, None, fit_within(query.width, query.height, face))
+            .map_err(gui_error("draw a tile"))
+    }
+}
+
+/// Shrinks a cell so neighbouring cards have a gap between them.
+fn leave_gap(rect: Rect) -> Rect {
+    let margin = rect.width() / 20;
+    Rect::new(
+        rect.x() + margin as i32,
+        rect.y() + margin as i32,
+        rect.width() - 2 * margin,
+        rect.height() - 2 * margin,
+    )
+}
+
+/// The face and bottom edge colours of a card.
+fn card_colours(value: u32) -> (Color, Color) {
+    match value {
+        1 => (Color::RGB(102, 204, 255), Color::RGB(70, 150, 200)),
+        2 => (Color::RGB(255, 102, 128), Color::RGB(200, 70, 100)),
+        v if v >= HIGH_CARD => (Color::RGB(255, 236, 170), Color::RGB(214, 160, 30)),
+        _ => (Color::RGB(255, 255, 255), Color::RGB(255, 204, 102)),
+    }
+}
+
+fn label_colour(value: u32) -> Color {
+    match value {
+        1 | 2 => Color::RGB(255, 255, 255),
+        v if v >= HIGH_CARD => Color::RGB(255, 82, 102),
+        _ => Color::RGB(0, 0, 0),
+    }
+}
+
+/// The largest rect with the given aspect ratio that fits in the middle of
+/// `outer`, leaving a margin around the edge.
+fn fit_within(width: u32, height: u32, outer: Rect) -> Rect {
+    let max_width = outer.width() as f32 * 0.8;
+    let max_height = outer.height() as f32 * 0.6;
+    let scale = (max_width / width as f32).min(max_height / height as f32);
+    let (width, height) = (
+        (width as f32 * scale) as u32,
+        (height as f32 * scale) as u32,
+    );
+    Rect::from_center(outer.center(), width, height)
+}
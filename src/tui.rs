@@ -0,0 +1,388 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use std::panic;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use persist::{autosave, record_replay};
+use theme::{queue_styled, CellStyle, Theme};
+use threes::{Board, Direction, Recorder, ThreesError, DEFAULT_HISTORY_LIMIT};
+
+/// Width of a grid cell, not counting its borders
+const CELL_WIDTH: usize = 7;
+/// Width inside each of the next card, score and move count panels. Three
+/// panels line up with the four cells of the grid.
+const PANEL_WIDTH: usize = 9;
+
+/// Something the player asked for with a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Move(Direction),
+    Undo,
+    Redo,
+    NewGame,
+    Quit,
+}
+
+type PanicHook = Box<dyn Fn(&panic::PanicHookInfo) + Sync + Send>;
+
+/// Keeps the terminal in raw mode on the alternate screen for as long as it's
+/// alive, and puts it back how it was when dropped.
+struct RawScreen {
+    /// The panic hook from before the screen was entered
+    print_panic: Arc<PanicHook>,
+}
+
+impl RawScreen {
+    fn enter() -> Result<RawScreen, ThreesError> {
+        terminal::enable_raw_mode()?;
+        let screen = RawScreen {
+            print_panic: Arc::new(panic::take_hook()),
+        };
+        // A panic message printed on the alternate screen would vanish with
+        // it, so put the terminal back before the message is written
+        let print_panic = Arc::clone(&screen.print_panic);
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            print_panic(info);
+        }));
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        restore_terminal();
+        // Panic hooks can't be changed while panicking, and ours is harmless
+        // once the terminal is back to normal
+        if !thread::panicking() {
+            let _ = panic::take_hook();
+            let print_panic = Arc::clone(&self.print_panic);
+            panic::set_hook(Box::new(move |info| print_panic(info)));
+        }
+    }
+}
+
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Plays in the terminal full screen, reading single keypresses: the arrow
+/// keys or WASD move, U or Z undoes, Y redoes and Q, ESC or Ctrl-C quits.
+/// Once the game is over, R or N starts another.
+pub fn play(
+    mut board: Board,
+    save_path: Option<PathBuf>,
+    record_path: Option<PathBuf>,
+    theme: Theme,
+) -> Result<(), ThreesError> {
+    let mut recorder = Recorder::new(&board);
+    {
+        let _screen = RawScreen::enter()?;
+        let mut out = io::stdout();
+        let mut message = "";
+        loop {
+            draw(&mut out, &board, theme, message)?;
+            message = "";
+            let key = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                // Anything else, like the terminal being resized, just redraws
+                _ => continue,
+            };
+            let game_over = !board.has_moves();
+            let changed = match key_command(key) {
+                Some(Command::Quit) => break,
+                Some(Command::NewGame) if game_over => {
+                    board = Board::new();
+                    board.set_history_limit(DEFAULT_HISTORY_LIMIT);
+                    recorder = Recorder::new(&board);
+                    true
+                }
+                _ if game_over => false,
+                Some(Command::Move(direction)) => {
+                    let moved = board.apply(direction)?.moved;
+                    if !moved {
+                        message = "Nothing can move that way";
+                    }
+                    moved
+                }
+                Some(Command::Undo) => {
+                    let undone = board.undo();
+                    if !undone {
+                        message = "Nothing to undo";
+                    }
+                    undone
+                }
+                Some(Command::Redo) => {
+                    let redone = board.redo();
+                    if !redone {
+                        message = "Nothing to redo";
+                    }
+                    redone
+                }
+                Some(Command::NewGame) | None => {
+                    message = "Move with the arrow keys or WASD";
+                    false
+                }
+            };
+            if changed {
+                recorder.sync(&board);
+                // Save as we go, so closing the terminal never loses a game
+                autosave(&board, &save_path);
+                if !board.has_moves() {
+                    record_replay(&board, &recorder, &record_path);
+                }
+            }
+        }
+    }
+    if !board.has_moves() {
+        println!("You scored: {}", board.calculate_score());
+    }
+    Ok(())
+}
+
+fn key_command(key: KeyEvent) -> Option<Command> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        // Raw mode turns Ctrl-C into an ordinary keypress
+        return match key.code {
+            KeyCode::Char('c') | KeyCode::Char('C') => Some(Command::Quit),
+            _ => None,
+        };
+    }
+    let command = match key.code {
+        KeyCode::Up => Command::Move(Direction::Up),
+        KeyCode::Down => Command::Move(Direction::Down),
+        KeyCode::Left => Command::Move(Direction::Left),
+        KeyCode::Right => Command::Move(Direction::Right),
+        KeyCode::Esc => Command::Quit,
+        KeyCode::Char(c) => match c.to_ascii_lowercase() {
+            'w' => Command::Move(Direction::Up),
+            's' => Command::Move(Direction::Down),
+            'a' => Command::Move(Direction::Left),
+            'd' => Command::Move(Direction::Right),
+            'u' | 'z' => Command::Undo,
+            'y' => Command::Redo,
+            'r' | 'n' => Command::NewGame,
+            'q' => Command::Quit,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Redraws the whole screen in place: the panels, the grid, the keys and
+/// `message` underneath.
+fn draw<W: Write>(out: &mut W, board: &Board, theme: Theme, message: &str) -> io::Result<()> {
+    let mut row = 0;
+    queue!(out, MoveTo(0, 0))?;
+    for line in board_lines(board, theme) {
+        write_line(out, &line)?;
+        end_line(out, &mut row)?;
+    }
+    if board.has_moves() {
+        queue!(out, Print("Arrows/WASD move, U undo, Y redo, Q quit"))?;
+    } else {
+        queue!(
+            out,
+            Print(format!(
+                "Game over! You scored {}. R plays again, Q quits",
+                board.calculate_score()
+            ))
+        )?;
+    }
+    end_line(out, &mut row)?;
+    queue!(out, Print(message), Clear(ClearType::FromCursorDown))?;
+    out.flush()
+}
+
+/// Prints the board below whatever is already on the terminal, for when it
+/// can't be drawn full screen.
+pub fn print_board(board: &Board, theme: Theme) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in board_lines(board, theme) {
+        write_line(&mut out, &line)?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+/// Some text and how to draw it.
+type Span = (String, CellStyle);
+
+/// The next card, score and move count panels with the boxed grid beneath
+/// them, as lines of styled text.
+fn board_lines(board: &Board, theme: Theme) -> Vec<Vec<Span>> {
+    let plain = |text: String| (text, CellStyle::default());
+    let high_card = board.get_high_card();
+    let mut lines = vec![];
+
+    let panels = [
+        ("Next", board.next_card_hint()),
+        ("Score", board.calculate_score().to_string()),
+        ("Moves", board.get_moves().len().to_string()),
+    ];
+    let border = "─".repeat(PANEL_WIDTH);
+    lines.push(vec![plain(format!("┌{}┐", border).repeat(panels.len()))]);
+    lines.push(
+        panels
+            .iter()
+            .map(|&(title, _)| plain(format!("│{:^w$}│", title, w = PANEL_WIDTH)))
+            .collect(),
+    );
+    let mut values = vec![];
+    for (i, (_, value)) in panels.iter().enumerate() {
+        values.push(plain("│".to_string()));
+        let text = format!("{:^w$}", value, w = PANEL_WIDTH);
+        values.push(if i == 0 {
+            // The next card can't be the high card, whatever its value
+            (text, theme.cell(board.get_next_card(), u32::MAX))
+        } else {
+            plain(text)
+        });
+        values.push(plain("│".to_string()));
+    }
+    lines.push(values);
+    lines.push(vec![plain(format!("└{}┘", border).repeat(panels.len()))]);
+
+    let cell_border = "─".repeat(CELL_WIDTH);
+    let rule = |left: &str, middle: &str, right: &str| {
+        let inner = [cell_border.as_str(); 4].join(middle);
+        vec![plain(format!("{}{}{}", left, inner, right))]
+    };
+    lines.push(rule("┌", "┬", "┐"));
+    for (row_num, cells) in board.get_board().iter().enumerate() {
+        if row_num > 0 {
+            lines.push(rule("├", "┼", "┤"));
+        }
+        // Each cell is three lines tall, with the value in the middle
+        for part in 0..3 {
+            let mut line = vec![plain("│".to_string())];
+            for &value in cells {
+                let text = if part == 1 && value != 0 {
+                    value.to_string()
+                } else {
+                    String::new()
+                };
+                line.push((
+                    format!("{:^w$}", text, w = CELL_WIDTH),
+                    theme.cell(value, high_card),
+                ));
+                line.push(plain("│".to_string()));
+            }
+            lines.push(line);
+        }
+    }
+    lines.push(rule("└", "┴", "┘"));
+    lines
+}
+
+fn write_line<W: Write>(out: &mut W, line: &[Span]) -> io::Result<()> {
+    for &(ref text, style) in line {
+        queue_styled(out, text, style)?;
+    }
+    Ok(())
+}
+
+/// Clears the rest of the line and moves to the start of the next one. Raw
+/// mode doesn't do that for a newline.
+fn end_line<W: Write>(out: &mut W, row: &mut u16) -> io::Result<()> {
+    *row += 1;
+    queue!(out, Clear(ClearType::UntilNewLine), MoveTo(0, *row))
+}
+
+#[cfg(test)]
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<Command> {
+    key_command(KeyEvent::new(code, modifiers))
+}
+
+#[test]
+fn test_key_command() {
+    let none = KeyModifiers::NONE;
+    assert_eq!(key(KeyCode::Up, none), Some(Command::Move(Direction::Up)));
+    assert_eq!(
+        key(KeyCode::Down, none),
+        Some(Command::Move(Direction::Down))
+    );
+    assert_eq!(
+        key(KeyCode::Left, none),
+        Some(Command::Move(Direction::Left))
+    );
+    assert_eq!(
+        key(KeyCode::Right, none),
+        Some(Command::Move(Direction::Right))
+    );
+    for &(c, direction) in [
+        ('w', Direction::Up),
+        ('a', Direction::Left),
+        ('s', Direction::Down),
+        ('d', Direction::Right),
+    ]
+    .iter()
+    {
+        assert_eq!(key(KeyCode::Char(c), none), Some(Command::Move(direction)));
+        let upper = c.to_ascii_uppercase();
+        assert_eq!(
+            key(KeyCode::Char(upper), KeyModifiers::SHIFT),
+            Some(Command::Move(direction))
+        );
+    }
+    assert_eq!(key(KeyCode::Char('q'), none), Some(Command::Quit));
+    assert_eq!(key(KeyCode::Esc, none), Some(Command::Quit));
+    assert_eq!(key(KeyCode::Char('r'), none), Some(Command::NewGame));
+    assert_eq!(key(KeyCode::Char('u'), none), Some(Command::Undo));
+    assert_eq!(key(KeyCode::Char('y'), none), Some(Command::Redo));
+    assert_eq!(key(KeyCode::Char('x'), none), None);
+}
+
+#[test]
+fn test_ctrl_c_quits_but_other_ctrl_keys_do_nothing() {
+    let control = KeyModifiers::CONTROL;
+    assert_eq!(key(KeyCode::Char('c'), control), Some(Command::Quit));
+    // Ctrl with a game key mustn't move
+    assert_eq!(key(KeyCode::Char('w'), control), None);
+    assert_eq!(key(KeyCode::Up, control), None);
+}
+
+#[test]
+fn test_board_lines() {
+    let board = Board::with_seed(42);
+    let lines = board_lines(&board, Theme::Plain);
+    let text: Vec<String> = lines
+        .iter()
+        .map(|line| line.iter().map(|span| span.0.as_str()).collect())
+        .collect();
+    // Four lines of panels, then three lines per row between the rules
+    assert_eq!(text.len(), 4 + 1 + 4 * 3 + 3 + 1);
+    let width = text[0].chars().count();
+    assert!(text.iter().all(|line| line.chars().count() == width));
+    assert!(text[2].contains(&board.calculate_score().to_string()));
+
+    for (row_num, row) in board.get_board().iter().enumerate() {
+        // The value sits on the middle of each cell's three lines
+        let cells: Vec<String> = text[4 + 1 + row_num * 4 + 1]
+            .split('│')
+            .filter(|cell| !cell.is_empty())
+            .map(|cell| cell.trim().to_string())
+            .collect();
+        let expected: Vec<String> = row
+            .iter()
+            .map(|&value| {
+                if value == 0 {
+                    String::new()
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect();
+        assert_eq!(cells, expected);
+    }
+}